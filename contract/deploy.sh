@@ -1,5 +1,10 @@
 #!/bin/sh
 
+if [ -z "$OWNER_ID" ]; then
+  echo ">> Set OWNER_ID to the account that will own the contract"
+  exit 1
+fi
+
 ./build.sh
 
 if [ $? -ne 0 ]; then
@@ -10,4 +15,4 @@ fi
 echo ">> Deploying contract"
 
 # https://docs.near.org/tools/near-cli#near-dev-deploy
-near dev-deploy --wasmFile ./target/wasm32-unknown-unknown/release/hello_near.wasm
+near dev-deploy --wasmFile ./target/wasm32-unknown-unknown/release/hello_near.wasm --initFunction new --initArgs "{\"owner_id\": \"$OWNER_ID\"}"
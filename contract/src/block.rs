@@ -0,0 +1,160 @@
+use crate::*;
+
+#[near_bindgen]
+impl Posts {
+    //function to block an account, it can no longer comment on, react to or donate
+    //to the caller's posts, nor mention the caller
+    pub fn block_account(&mut self, account_id: AccountId) {
+        let blocker = env::predecessor_account_id();
+        require!(blocker != account_id, "Can't block yourself");
+        let initial_usage = env::storage_usage();
+        let mut blocked = Self::internal_account_set(&self.blocked, &blocker, "blocked");
+        blocked.insert(&account_id);
+        self.blocked.insert(&blocker, &blocked);
+        self.internal_charge_storage(&blocker, initial_usage);
+    }
+
+    pub fn unblock_account(&mut self, account_id: AccountId) {
+        let blocker = env::predecessor_account_id();
+        let initial_usage = env::storage_usage();
+        Self::internal_remove_edge(&mut self.blocked, &blocker, &account_id);
+        self.internal_charge_storage(&blocker, initial_usage);
+    }
+
+    //function to mute an account, its posts are left out of the caller's feed and searches
+    pub fn mute_account(&mut self, account_id: AccountId) {
+        let muter = env::predecessor_account_id();
+        require!(muter != account_id, "Can't mute yourself");
+        let initial_usage = env::storage_usage();
+        let mut muted = Self::internal_account_set(&self.muted, &muter, "muted");
+        muted.insert(&account_id);
+        self.muted.insert(&muter, &muted);
+        self.internal_charge_storage(&muter, initial_usage);
+    }
+
+    pub fn unmute_account(&mut self, account_id: AccountId) {
+        let muter = env::predecessor_account_id();
+        let initial_usage = env::storage_usage();
+        Self::internal_remove_edge(&mut self.muted, &muter, &account_id);
+        self.internal_charge_storage(&muter, initial_usage);
+    }
+
+    pub fn get_blocked(&self, account_id: AccountId, from_index: Option<U64>, limit: Option<u64>) -> Vec<AccountId> {
+        Self::internal_page_set(self.blocked.get(&account_id), from_index, limit)
+    }
+
+    pub fn get_muted(&self, account_id: AccountId, from_index: Option<U64>, limit: Option<u64>) -> Vec<AccountId> {
+        Self::internal_page_set(self.muted.get(&account_id), from_index, limit)
+    }
+
+    //whether `blocker` blocked `account_id`
+    pub fn is_blocked(&self, blocker: AccountId, account_id: AccountId) -> bool {
+        self.internal_is_blocked(&blocker, &account_id)
+    }
+}
+
+impl Posts {
+    pub(crate) fn internal_is_blocked(&self, blocker: &AccountId, account_id: &AccountId) -> bool {
+        self.blocked.get(blocker).map(|blocked| blocked.contains(account_id)).unwrap_or(false)
+    }
+
+    pub(crate) fn internal_is_muted(&self, muter: &AccountId, account_id: &AccountId) -> bool {
+        self.muted.get(muter).map(|muted| muted.contains(account_id)).unwrap_or(false)
+    }
+
+    //stops accounts the author of a post blocked from interacting with it
+    pub(crate) fn assert_not_blocked(&self, post: &Post, account_id: &AccountId) {
+        require!(!self.internal_is_blocked(&post.author, account_id), "The author of this post blocked you");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{ ids, new_contract_with_post, set_caller, set_deposit };
+    use near_sdk::test_utils::accounts;
+
+    //post "0" by accounts(1), who blocked accounts(2)
+    fn setup() -> Posts {
+        let mut post = new_contract_with_post();
+        post.block_account(accounts(2));
+        set_caller(accounts(2));
+        post
+    }
+
+    //test blocking and unblocking
+    #[test]
+    pub fn block_and_unblock() {
+        let mut post = setup();
+        assert!(post.is_blocked(accounts(1), accounts(2)));
+        assert!(!post.is_blocked(accounts(2), accounts(1)));
+        assert_eq!(post.get_blocked(accounts(1), None, None), vec![accounts(2)]);
+        set_caller(accounts(1));
+        post.unblock_account(accounts(2));
+        assert!(!post.is_blocked(accounts(1), accounts(2)));
+        set_caller(accounts(2));
+        post.add_comment("0".to_string(), "hello".to_string(), None);
+    }
+
+    //test blocked accounts can't comment
+    #[test]
+    #[should_panic(expected = "The author of this post blocked you")]
+    pub fn blocked_comment() {
+        let mut post = setup();
+        post.add_comment("0".to_string(), "hello".to_string(), None);
+    }
+
+    //test blocked accounts can't react
+    #[test]
+    #[should_panic(expected = "The author of this post blocked you")]
+    pub fn blocked_reaction() {
+        let mut post = setup();
+        post.like_post("0".to_string());
+    }
+
+    //test blocked accounts can't donate, the panic hands the deposit back
+    #[test]
+    #[should_panic(expected = "The author of this post blocked you")]
+    pub fn blocked_donation() {
+        let mut post = setup();
+        set_deposit(accounts(2), 100);
+        post.donate_author("0".to_string(), U128::from(100), None);
+    }
+
+    //test mentions of accounts that blocked the author are dropped
+    #[test]
+    pub fn blocked_mention() {
+        let mut post = setup();
+        post.new_post("hi @bob and @charlie".to_string(), "body".to_string(), None);
+        assert_eq!(post.get_post("1".to_string()).unwrap().mentions, vec![accounts(2)]);
+        assert!(post.get_mentions(accounts(1), None, None).is_empty());
+    }
+
+    //test muted authors are left out of the feed and searches
+    #[test]
+    pub fn muted_authors() {
+        let mut post = setup();
+        set_caller(accounts(3));
+        post.new_post("title".to_string(), "body".to_string(), None);
+        set_caller(accounts(4));
+        post.follow(accounts(1));
+        post.follow(accounts(3));
+        post.mute_account(accounts(3));
+        assert_eq!(post.get_muted(accounts(4), None, None), vec![accounts(3)]);
+
+        assert_eq!(ids(&post.get_feed(accounts(4), None, None).posts), vec!["0"]);
+        assert_eq!(ids(&post.search_posts("title".to_string(), None, None, Some(accounts(4)), None)), vec!["0"]);
+        assert_eq!(ids(&post.search_posts("title".to_string(), None, None, None, None)), vec!["0", "1"]);
+
+        post.unmute_account(accounts(3));
+        assert_eq!(ids(&post.get_feed(accounts(4), None, None).posts), vec!["1", "0"]);
+    }
+
+    //test muting yourself
+    #[test]
+    #[should_panic(expected = "Can't mute yourself")]
+    pub fn mute_yourself() {
+        let mut post = setup();
+        post.mute_account(accounts(2));
+    }
+}
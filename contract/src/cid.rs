@@ -0,0 +1,194 @@
+//validation of IPFS content identifiers, see https://github.com/multiformats/cid
+//CIDv0 is a base58btc sha2-256 multihash ("Qm..."), CIDv1 is a multibase string
+//of <version><codec><multihash> where every number is an unsigned varint
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BASE32_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+//multihash code and digest length of sha2-256
+const SHA2_256: u64 = 0x12;
+const SHA2_256_LEN: u64 = 32;
+//longest digest accepted in a CIDv1 multihash
+const MAX_DIGEST_LEN: u64 = 128;
+//longest CID string accepted, well above any real world hash
+const MAX_CID_LEN: usize = 256;
+
+//function to check that a string is a well formed CIDv0 or CIDv1
+pub fn validate_cid(cid: &str) -> Result<(), &'static str> {
+    if cid.is_empty() {
+        return Err("empty CID");
+    }
+    if cid.len() > MAX_CID_LEN {
+        return Err("CID is too long");
+    }
+    if !cid.is_ascii() {
+        return Err("CID must be ASCII");
+    }
+    if cid.len() == 46 && cid.starts_with("Qm") {
+        return validate_v0(cid);
+    }
+    let (prefix, rest) = cid.split_at(1);
+    let bytes = match prefix {
+        "b" => decode_base32(rest)?,
+        "B" => decode_base32(&rest.to_ascii_lowercase())?,
+        "z" => decode_base58(rest)?,
+        "f" => decode_base16(rest)?,
+        "F" => decode_base16(&rest.to_ascii_lowercase())?,
+        _ => return Err("unsupported multibase prefix"),
+    };
+    validate_v1(&bytes)
+}
+
+fn validate_v0(cid: &str) -> Result<(), &'static str> {
+    let bytes = decode_base58(cid)?;
+    if bytes.len() != 34 || bytes[0] != SHA2_256 as u8 || bytes[1] != SHA2_256_LEN as u8 {
+        return Err("CIDv0 must be a sha2-256 multihash");
+    }
+    Ok(())
+}
+
+fn validate_v1(bytes: &[u8]) -> Result<(), &'static str> {
+    let mut rest = bytes;
+    if read_varint(&mut rest)? != 1 {
+        return Err("unsupported CID version");
+    }
+    //content codec, any registered multicodec is accepted
+    read_varint(&mut rest)?;
+    let hash_code = read_varint(&mut rest)?;
+    let digest_len = read_varint(&mut rest)?;
+    if digest_len == 0 || digest_len > MAX_DIGEST_LEN {
+        return Err("invalid multihash digest length");
+    }
+    if hash_code == SHA2_256 && digest_len != SHA2_256_LEN {
+        return Err("invalid sha2-256 digest length");
+    }
+    if rest.len() as u64 != digest_len {
+        return Err("multihash digest length mismatch");
+    }
+    Ok(())
+}
+
+//reads an unsigned LEB128 varint of at most 9 bytes, as used by multiformats
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, &'static str> {
+    let mut value: u64 = 0;
+    for (i, byte) in bytes.iter().enumerate().take(9) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            if *byte == 0 && i > 0 {
+                return Err("varint is not minimally encoded");
+            }
+            *bytes = &bytes[i + 1..];
+            return Ok(value);
+        }
+    }
+    Err("truncated or oversized varint")
+}
+
+fn decode_base58(input: &str) -> Result<Vec<u8>, &'static str> {
+    //little endian base 256 digits of the decoded number
+    let mut bytes: Vec<u8> = Vec::with_capacity(input.len());
+    for c in input.bytes() {
+        let mut carry = BASE58_ALPHABET
+            .iter()
+            .position(|a| *a == c)
+            .ok_or("invalid base58 character")? as u32;
+        for byte in bytes.iter_mut() {
+            carry += u32::from(*byte) * 58;
+            *byte = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push((carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+    //every leading '1' stands for a leading zero byte
+    let zeros = input.bytes().take_while(|c| *c == b'1').count();
+    bytes.resize(bytes.len() + zeros, 0);
+    bytes.reverse();
+    Ok(bytes)
+}
+
+fn decode_base32(input: &str) -> Result<Vec<u8>, &'static str> {
+    let mut bytes = Vec::with_capacity(input.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in input.bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a == c)
+            .ok_or("invalid base32 character")? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    if bits >= 5 || buffer != 0 {
+        return Err("invalid base32 padding");
+    }
+    Ok(bytes)
+}
+
+fn decode_base16(input: &str) -> Result<Vec<u8>, &'static str> {
+    let pairs = input.as_bytes().chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return Err("invalid base16 length");
+    }
+    pairs
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or("invalid base16 character")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //test well formed CIDs of both versions
+    #[test]
+    pub fn accepts_valid_cids() {
+        assert_eq!(validate_cid("QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o"), Ok(()));
+        assert_eq!(
+            validate_cid("bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi"),
+            Ok(())
+        );
+        assert_eq!(
+            validate_cid("bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e"),
+            Ok(())
+        );
+        assert_eq!(
+            validate_cid("zdj7WWeQ43G6JJvLWQWZpyHuAMq6uYWRjkBXFad11vE2LHhQ7"),
+            Ok(())
+        );
+        assert_eq!(
+            validate_cid(
+                "f01701220c3c4733ec8affd06cf9e9ff50ffc6bcd2ec85a6170004bb709669c31de94391a"
+            ),
+            Ok(())
+        );
+    }
+
+    //test malformed CIDs are rejected
+    #[test]
+    pub fn rejects_malformed_cids() {
+        assert!(validate_cid("").is_err());
+        assert!(validate_cid("https://static.vecteezy.com/image.jpg").is_err());
+        //CIDv0 with a character outside the base58 alphabet
+        assert!(validate_cid("QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5O").is_err());
+        //CIDv0 that is one character short
+        assert!(validate_cid("QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5").is_err());
+        //CIDv1 with a truncated digest
+        assert!(validate_cid("bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbz").is_err());
+        //CIDv1 with an unknown version
+        assert!(validate_cid("f02701220c3c4733ec8affd06cf9e9ff50ffc6bcd2ec85a6170004bb709669c31de94391a").is_err());
+        assert!(validate_cid(&"b".repeat(300)).is_err());
+        assert!(validate_cid("éafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi").is_err());
+    }
+}
@@ -0,0 +1,349 @@
+use crate::*;
+
+//longest comment body accepted
+pub const MAX_COMMENT_LEN: usize = 2_000;
+//most comments returned by a single tree view, deeper replies are paged with `get_replies`
+pub const MAX_COMMENT_TREE_SIZE: usize = 200;
+
+//a comment on a post, or a reply to another comment when `parent_id` is set
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Comment {
+    pub id: String,
+    pub post_id: String,
+    pub parent_id: Option<String>,
+    pub author: AccountId,
+    pub body: String,
+    //block timestamp in nanoseconds of when the comment was made
+    pub timestamp: U64,
+    //hidden by the post author or a moderator, the body is left out of views
+    pub hidden: bool,
+    //deleted by its author while it still had replies, kept so the thread holds together
+    pub deleted: bool,
+}
+
+//a comment with the first page of its replies
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CommentNode {
+    pub comment: Comment,
+    //number of direct replies, more than `replies` when the tree was cut short
+    pub reply_count: u64,
+    pub replies: Vec<CommentNode>,
+}
+
+#[near_bindgen]
+impl Posts {
+    //function to comment on a post, or to reply to one of its comments with `parent_id`,
+    //returns the id of the new comment
+    pub fn add_comment(&mut self, post_id: String, body: String, parent_id: Option<String>) -> String {
+        require!(!body.trim().is_empty(), "Comment can't be empty");
+        require!(body.len() <= MAX_COMMENT_LEN, "Comment is too long");
+        let post = self.internal_get_post(&post_id).expect("Post not found");
+        self.assert_not_blocked(&post, &env::predecessor_account_id());
+        if let Some(parent_id) = &parent_id {
+            let parent = self.comments.get(parent_id).expect("Comment not found");
+            require!(parent.post_id == post_id, "Reply must be on the same post as its parent comment");
+            require!(!parent.deleted, "Can't reply to a deleted comment");
+        }
+        let seq = self.next_comment_seq;
+        self.next_comment_seq += 1;
+        let comment = Comment {
+            id: seq.to_string(),
+            post_id,
+            parent_id,
+            author: env::predecessor_account_id(),
+            body,
+            timestamp: U64::from(env::block_timestamp()),
+            hidden: false,
+            deleted: false,
+        };
+        let parent_key = Self::comment_parent_key(&comment);
+        let mut siblings = self.comment_threads.get(&parent_key).unwrap_or_else(|| {
+            TreeMap::new(StorageKey::CommentThread { parent_hash: env::sha256_array(parent_key.as_bytes()) })
+        });
+        siblings.insert(&seq, &comment.id);
+        self.comment_threads.insert(&parent_key, &siblings);
+        //the author pays for the comment, the threads are shared and kept by the contract
+        let initial_usage = env::storage_usage();
+        self.comments.insert(&comment.id, &comment);
+        self.internal_charge_storage(&comment.author, initial_usage);
+        Event::Comment(vec![CommentData {
+            comment_id: &comment.id,
+            post_id: &comment.post_id,
+            parent_id: comment.parent_id.as_deref(),
+            author_id: &comment.author,
+        }]).emit();
+        comment.id
+    }
+
+    //function to delete a comment, author only, a comment with replies is
+    //blanked instead so the replies stay reachable
+    pub fn delete_comment(&mut self, comment_id: String) {
+        let mut comment = self.comments.get(&comment_id).expect("Comment not found");
+        require!(env::predecessor_account_id() == comment.author, "Only the author can delete this comment");
+        if self.comment_threads.get(&comment_id).is_some() {
+            let initial_usage = env::storage_usage();
+            comment.body = String::new();
+            comment.deleted = true;
+            self.comments.insert(&comment_id, &comment);
+            self.internal_charge_storage(&comment.author, initial_usage);
+        } else {
+            self.internal_remove_comment(comment);
+        }
+    }
+
+    //function to hide a comment from the views, allowed to the author of the post and moderators
+    pub fn hide_comment(&mut self, comment_id: String) {
+        self.internal_set_comment_hidden(comment_id, true);
+    }
+
+    //function to show a hidden comment again, allowed to the author of the post and moderators
+    pub fn unhide_comment(&mut self, comment_id: String) {
+        self.internal_set_comment_hidden(comment_id, false);
+    }
+
+    pub fn get_comment(&self, comment_id: String) -> Option<Comment> {
+        self.comments.get(&comment_id).map(Self::comment_view)
+    }
+
+    //function to get a page of the comments on a post, oldest first, each with
+    //its replies nested up to MAX_COMMENT_TREE_SIZE comments in total
+    pub fn get_comments(&self, post_id: String, from_index: Option<U64>, limit: Option<u64>) -> Vec<CommentNode> {
+        let mut budget = MAX_COMMENT_TREE_SIZE;
+        self.internal_comment_tree(&format!("post:{}", post_id), from_index, limit, &mut budget)
+    }
+
+    //function to get a page of the replies to a comment, oldest first, nested like `get_comments`
+    pub fn get_replies(&self, comment_id: String, from_index: Option<U64>, limit: Option<u64>) -> Vec<CommentNode> {
+        let mut budget = MAX_COMMENT_TREE_SIZE;
+        self.internal_comment_tree(&comment_id, from_index, limit, &mut budget)
+    }
+}
+
+impl Posts {
+    //key of the thread a comment belongs to, comment ids and post ids share the
+    //number space so top level comments are keyed by "post:<post id>"
+    fn comment_parent_key(comment: &Comment) -> String {
+        match &comment.parent_id {
+            Some(parent_id) => parent_id.clone(),
+            None => format!("post:{}", comment.post_id),
+        }
+    }
+
+    //a comment as returned by the views, without the body when it is hidden
+    fn comment_view(mut comment: Comment) -> Comment {
+        if comment.hidden {
+            comment.body = String::new();
+        }
+        comment
+    }
+
+    //builds a page of the thread under `parent_key`, spending one unit of `budget` per comment
+    fn internal_comment_tree(
+        &self,
+        parent_key: &String,
+        from_index: Option<U64>,
+        limit: Option<u64>,
+        budget: &mut usize
+    ) -> Vec<CommentNode> {
+        let (from_index, limit) = Self::page(from_index, limit);
+        let thread = match self.comment_threads.get(parent_key) {
+            Some(thread) => thread,
+            None => return Vec::new(),
+        };
+        let mut nodes = Vec::new();
+        for (_, comment_id) in thread.iter().skip(from_index).take(limit) {
+            if *budget == 0 {
+                break;
+            }
+            *budget -= 1;
+            let comment = self.comments.get(&comment_id).unwrap();
+            let reply_count = self.comment_threads.get(&comment_id).map(|replies| replies.len()).unwrap_or(0);
+            let replies = self.internal_comment_tree(&comment_id, None, None, budget);
+            nodes.push(CommentNode { comment: Self::comment_view(comment), reply_count, replies });
+        }
+        nodes
+    }
+
+    fn internal_set_comment_hidden(&mut self, comment_id: String, hidden: bool) {
+        let mut comment = self.comments.get(&comment_id).expect("Comment not found");
+        let caller = env::predecessor_account_id();
+        //moderators don't need the post, its comments outlive it when it is deleted
+        require!(
+            self.internal_has_role(&caller, Role::Moderator) ||
+                self.internal_get_post(&comment.post_id).is_some_and(|post| post.author == caller),
+            "Only the author of the post or a moderator can hide its comments"
+        );
+        comment.hidden = hidden;
+        self.comments.insert(&comment_id, &comment);
+    }
+
+    //removes a comment without replies, then any deleted parent it was the last reply of
+    fn internal_remove_comment(&mut self, comment: Comment) {
+        let mut next = Some(comment);
+        while let Some(comment) = next.take() {
+            //each removed comment is credited to its own author
+            let initial_usage = env::storage_usage();
+            self.comments.remove(&comment.id);
+            self.internal_charge_storage(&comment.author, initial_usage);
+            let parent_key = Self::comment_parent_key(&comment);
+            let mut has_siblings = false;
+            if let Some(mut siblings) = self.comment_threads.get(&parent_key) {
+                siblings.remove(&comment.id.parse().unwrap());
+                has_siblings = !siblings.is_empty();
+                if has_siblings {
+                    self.comment_threads.insert(&parent_key, &siblings);
+                } else {
+                    self.comment_threads.remove(&parent_key);
+                }
+            }
+            if has_siblings {
+                continue;
+            }
+            next = comment.parent_id.and_then(|parent_id| self.comments.get(&parent_id)).filter(|parent| parent.deleted);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{ new_contract_with_post, set_caller };
+    use near_sdk::test_utils::accounts;
+
+    fn ids(nodes: &[CommentNode]) -> Vec<String> {
+        nodes.iter().map(|node| node.comment.id.clone()).collect()
+    }
+
+    fn comment(post: &mut Posts, author: AccountId, body: &str, parent_id: Option<&str>) -> String {
+        set_caller(author);
+        post.add_comment("0".to_string(), body.to_string(), parent_id.map(|id| id.to_string()))
+    }
+
+    //test comments and replies come back as a paginated tree
+    #[test]
+    pub fn comment_tree() {
+        let mut post = new_contract_with_post();
+        let first = comment(&mut post, accounts(2), "first", None);
+        let reply = comment(&mut post, accounts(3), "reply", Some(&first));
+        comment(&mut post, accounts(2), "nested", Some(&reply));
+        comment(&mut post, accounts(3), "second", None);
+        comment(&mut post, accounts(4), "third", None);
+
+        let tree = post.get_comments("0".to_string(), None, None);
+        assert_eq!(ids(&tree), vec!["0", "3", "4"]);
+        assert_eq!(tree[0].comment.author, accounts(2));
+        assert_eq!(tree[0].reply_count, 1);
+        assert_eq!(ids(&tree[0].replies), vec!["1"]);
+        assert_eq!(tree[0].replies[0].replies[0].comment.body, "nested".to_string());
+        assert_eq!(ids(&post.get_comments("0".to_string(), Some(U64::from(1)), Some(1))), vec!["3"]);
+        assert_eq!(ids(&post.get_replies(first, None, None)), vec!["1"]);
+        assert_eq!(post.get_comment(reply).unwrap().parent_id, Some("0".to_string()));
+    }
+
+    //test replies must stay on the post of their parent
+    #[test]
+    #[should_panic(expected = "Reply must be on the same post as its parent comment")]
+    pub fn reply_on_other_post() {
+        let mut post = new_contract_with_post();
+        post.new_post("other".to_string(), "body".to_string(), None);
+        let first = comment(&mut post, accounts(2), "first", None);
+        post.add_comment("1".to_string(), "reply".to_string(), Some(first));
+    }
+
+    //test comments on posts that don't exist
+    #[test]
+    #[should_panic(expected = "Post not found")]
+    pub fn comment_missing_post() {
+        let mut post = new_contract_with_post();
+        post.add_comment("7".to_string(), "body".to_string(), None);
+    }
+
+    //test deleting keeps the thread of a comment with replies
+    #[test]
+    pub fn delete_comments() {
+        let mut post = new_contract_with_post();
+        let first = comment(&mut post, accounts(2), "first", None);
+        let reply = comment(&mut post, accounts(3), "reply", Some(&first));
+        let other = comment(&mut post, accounts(2), "other", None);
+
+        set_caller(accounts(2));
+        post.delete_comment(other.clone());
+        assert!(post.get_comment(other).is_none());
+        post.delete_comment(first.clone());
+        let tree = post.get_comments("0".to_string(), None, None);
+        assert_eq!(ids(&tree), vec!["0"]);
+        assert!(tree[0].comment.deleted);
+        assert!(tree[0].comment.body.is_empty());
+        assert_eq!(ids(&tree[0].replies), vec!["1"]);
+
+        //the last reply takes its deleted parent with it
+        set_caller(accounts(3));
+        post.delete_comment(reply);
+        assert!(post.get_comments("0".to_string(), None, None).is_empty());
+        assert!(post.get_comment(first).is_none());
+    }
+
+    //test only the author deletes a comment
+    #[test]
+    #[should_panic(expected = "Only the author can delete this comment")]
+    pub fn delete_comment_not_author() {
+        let mut post = new_contract_with_post();
+        let first = comment(&mut post, accounts(2), "first", None);
+        set_caller(accounts(1));
+        post.delete_comment(first);
+    }
+
+    //test the post author hides and restores a comment
+    #[test]
+    pub fn hide_comments() {
+        let mut post = new_contract_with_post();
+        let first = comment(&mut post, accounts(2), "spam", None);
+        set_caller(accounts(1));
+        post.hide_comment(first.clone());
+        let hidden = post.get_comment(first.clone()).unwrap();
+        assert!(hidden.hidden);
+        assert!(hidden.body.is_empty());
+        post.unhide_comment(first.clone());
+        assert_eq!(post.get_comments("0".to_string(), None, None)[0].comment.body, "spam".to_string());
+    }
+
+    //test moderators hide comments left on deleted posts
+    #[test]
+    pub fn hide_comment_deleted_post() {
+        let mut post = new_contract_with_post();
+        let first = comment(&mut post, accounts(2), "spam", None);
+        set_caller(accounts(1));
+        post.delete_post("0".to_string());
+        set_caller(accounts(0));
+        post.hide_comment(first.clone());
+        assert!(post.get_comment(first).unwrap().hidden);
+    }
+
+    //test only the post author hides comments
+    #[test]
+    #[should_panic(expected = "Only the author of the post or a moderator can hide its comments")]
+    pub fn hide_comment_not_post_author() {
+        let mut post = new_contract_with_post();
+        let first = comment(&mut post, accounts(2), "first", None);
+        post.hide_comment(first);
+    }
+
+    //test the tree view stops at MAX_COMMENT_TREE_SIZE comments
+    #[test]
+    pub fn comment_tree_is_capped() {
+        let mut post = new_contract_with_post();
+        let mut parent: Option<String> = None;
+        for _ in 0..MAX_COMMENT_TREE_SIZE + 5 {
+            parent = Some(comment(&mut post, accounts(2), "deeper", parent.as_deref()));
+        }
+        let mut depth = 0;
+        let mut nodes = post.get_comments("0".to_string(), None, None);
+        while let Some(node) = nodes.pop() {
+            depth += 1;
+            nodes = node.replies;
+        }
+        assert_eq!(depth, MAX_COMMENT_TREE_SIZE);
+    }
+}
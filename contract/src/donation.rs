@@ -0,0 +1,479 @@
+use crate::*;
+
+//gas reserved for `on_donation_complete`
+const GAS_FOR_DONATION_CALLBACK: Gas = Gas(10_000_000_000_000);
+//longest message accepted with a donation
+pub const MAX_DONATION_MESSAGE_LEN: usize = 280;
+//bytes a ledger entry can take besides its message, reserved from the donor's deposit while the transfer is in flight
+const DONATION_ENTRY_BYTES: StorageUsage = 200;
+
+//a single donation to a post
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Donation {
+    pub donor: AccountId,
+    pub amount: U128,
+    //block timestamp in nanoseconds of when the transfer went through
+    pub timestamp: U64,
+    pub message: Option<String>,
+}
+
+#[near_bindgen]
+impl Posts {
+    //function to donate a author of the post, the donation is the attached deposit
+    //and `amount` must match it, the deposit goes back to the donor if the post is gone.
+    //The entry in the post's ledger is charged to the donor's storage deposit, reserved up front
+    //so the callback never has to charge it
+    #[payable]
+    pub fn donate_author(&mut self, post_id: String, amount: U128, message: Option<String>) {
+        let deposit: Balance = env::attached_deposit();
+        require!(deposit > 0, "Attach a deposit to donate");
+        require!(amount.0 == deposit, "Donation amount must equal the attached deposit");
+        if let Some(message) = &message {
+            require!(message.len() <= MAX_DONATION_MESSAGE_LEN, "Donation message is too long");
+        }
+        let donor = env::predecessor_account_id();
+        match self.internal_get_post(&post_id) {
+            Some(post) => {
+                self.assert_not_blocked(&post, &donor);
+                self.internal_charge_bytes(&donor, Self::donation_entry_bytes(&message));
+                //the tally is only updated once the transfer went through
+                Promise::new(post.author).transfer(deposit).then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(GAS_FOR_DONATION_CALLBACK)
+                        .on_donation_complete(post_id, donor, U128::from(deposit), message)
+                );
+            }
+            None => {
+                // post not found, refund the donor
+                env::log_str(&format!("Couldn't find post '{}', refunding {}", post_id, donor));
+                Promise::new(donor).transfer(deposit);
+            }
+        }
+    }
+
+    //callback of the transfer started by `donate_author`, returns whether the donation went through.
+    //Hands back what was reserved for the ledger entry beyond what it takes, or all of it if nothing is recorded
+    #[private]
+    pub fn on_donation_complete(
+        &mut self,
+        post_id: String,
+        donor: AccountId,
+        amount: U128,
+        message: Option<String>,
+        #[callback_result] result: Result<(), PromiseError>
+    ) -> bool {
+        if result.is_err() {
+            //the transfer bounced back to the contract, hand it back to the donor
+            env::log_str(&format!("Donation to post '{}' failed, refunding {}", post_id, donor));
+            self.internal_refund_bytes(&donor, Self::donation_entry_bytes(&message));
+            Promise::new(donor).transfer(amount.0);
+            return false;
+        }
+        match self.internal_get_post(&post_id) {
+            Some(mut post) => {
+                let old_amount = post.donation_amount.0;
+                post.donation_amount = U128::from(old_amount + amount.0);
+                let new_amount = post.donation_amount.0;
+                self.internal_update_post(post.clone());
+                self.internal_rerank_post(&post_id, old_amount, new_amount);
+                Event::Donation(vec![DonationData {
+                    post_id: &post.id,
+                    author_id: &post.author,
+                    donor_id: &donor,
+                    amount,
+                    token_id: None,
+                }]).emit();
+                self.internal_record_donation(&post, Donation {
+                    donor,
+                    amount,
+                    timestamp: U64::from(env::block_timestamp()),
+                    message,
+                });
+            }
+            None => {
+                //deleted while the transfer was in flight, the author still got paid
+                env::log_str(&format!("Couldn't find post '{}' to record the donation", post_id));
+                self.internal_refund_bytes(&donor, Self::donation_entry_bytes(&message));
+            }
+        }
+        true
+    }
+
+    //function to get all donations from the post
+    pub fn get_donations(&mut self, post_id: String) -> Option<u128> {
+        self.internal_get_post(&post_id).map(|post| post.donation_amount.into())
+    }
+
+    //function to drop up to `limit` entries from the ledger of a deleted post, newest first,
+    //crediting each donor for its entry, returns how many are still left. Anyone can call it
+    pub fn clear_donations(&mut self, post_id: String, limit: Option<u64>) -> u64 {
+        require!(!self.posts.contains_key(&post_id), "Only donations to deleted posts can be cleared");
+        let (_, limit) = Self::page(None, limit);
+        let mut donations = match self.donations.get(&post_id) {
+            Some(donations) => donations,
+            None => return 0,
+        };
+        for _ in 0..limit {
+            let initial_usage = env::storage_usage();
+            match donations.pop() {
+                Some(donation) => self.internal_charge_storage(&donation.donor, initial_usage),
+                None => break,
+            }
+        }
+        if donations.is_empty() {
+            self.donations.remove(&post_id);
+        } else {
+            self.donations.insert(&post_id, &donations);
+        }
+        donations.len()
+    }
+
+    //function to list the donations made to a post, oldest first
+    pub fn get_post_donations(
+        &self,
+        post_id: String,
+        from_index: Option<U64>,
+        limit: Option<u64>
+    ) -> Vec<Donation> {
+        let (from_index, limit) = Self::page(from_index, limit);
+        if !self.posts.contains_key(&post_id) {
+            return Vec::new();
+        }
+        match self.donations.get(&post_id) {
+            Some(donations) => donations.iter().skip(from_index).take(limit).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    //function to get the total an account has donated
+    pub fn get_donor_total(&self, account_id: AccountId) -> U128 {
+        U128::from(self.donor_totals.get(&account_id).unwrap_or(0))
+    }
+
+    //function to get the total an account has received across all of its posts
+    pub fn get_author_total(&self, account_id: AccountId) -> U128 {
+        U128::from(self.author_totals.get(&account_id).unwrap_or(0))
+    }
+}
+
+impl Posts {
+    //bytes reserved from the donor's deposit for a ledger entry with `message`
+    fn donation_entry_bytes(message: &Option<String>) -> StorageUsage {
+        DONATION_ENTRY_BYTES + message.as_ref().map_or(0, |message| message.len()) as StorageUsage
+    }
+
+    //appends a donation to the post's ledger and the donor and author totals, the donor keeps
+    //paying for its entry out of what was reserved, the ledger itself and the totals are kept by the contract
+    fn internal_record_donation(&mut self, post: &Post, donation: Donation) {
+        let mut donations = self.donations.get(&post.id).unwrap_or_else(|| {
+            Vector::new(StorageKey::PostDonations { post_hash: env::sha256_array(post.id.as_bytes()) })
+        });
+        let initial_usage = env::storage_usage();
+        donations.push(&donation);
+        let entry_bytes = env::storage_usage() - initial_usage;
+        let reserved = Self::donation_entry_bytes(&donation.message);
+        self.internal_refund_bytes(&donation.donor, reserved.saturating_sub(entry_bytes));
+        self.donations.insert(&post.id, &donations);
+
+        let amount = donation.amount.0;
+        let donor_total = self.donor_totals.get(&donation.donor).unwrap_or(0);
+        self.donor_totals.insert(&donation.donor, &(donor_total + amount));
+        let author_total = self.author_totals.get(&post.author).unwrap_or(0);
+        self.author_totals.insert(&post.author, &(author_total + amount));
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{ new_contract, new_contract_with_post, set_caller, set_deposit };
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::mock::VmAction;
+    use near_sdk::test_utils::{ accounts, get_created_receipts, VMContextBuilder };
+    use near_sdk::testing_env;
+    //for testing purposes
+    use crate::IMAGE;
+
+    //test success donate function
+    #[test]
+    pub fn sucess_donate_author() {
+        set_caller(accounts(1));
+        let mut post = new_contract();
+        post.new_post("title".to_string(), "body".to_string(), Some(IMAGE.to_string()));
+        post.new_post("title 1".to_string(), "body 1".to_string(), Some(IMAGE.to_string()));
+        let post_id = post.get_posts()[0].id.to_string();
+        set_deposit(accounts(2), 100);
+        for _ in 0..3 {
+            post.donate_author(post_id.clone(), U128::from(100), None);
+            assert!(post.on_donation_complete(post_id.clone(), accounts(2), U128::from(100), None, Ok(())));
+        }
+        assert_eq!(post.get_posts()[0].donation_amount, U128::from(300));
+        assert_eq!(post.get_donations(post_id), Some(300));
+        let receipts = get_created_receipts();
+        assert_eq!(receipts[0].receiver_id, accounts(1));
+        assert_eq!(receipts[0].actions, vec![VmAction::Transfer { deposit: 100 }]);
+        //the transfer is chained to the callback on the contract itself
+        assert_eq!(receipts[1].receiver_id, env::current_account_id());
+        assert!(matches!(
+            &receipts[1].actions[0],
+            VmAction::FunctionCall { function_name, .. } if function_name == "on_donation_complete"
+        ));
+    }
+
+    //test failed donations are refunded and left out of the tally
+    #[test]
+    pub fn fail_donate_author() {
+        let mut post = new_contract();
+        post.new_post("title".to_string(), "body".to_string(), None);
+        post.new_post("title 1".to_string(), "body 1".to_string(), None);
+        let post_id = post.get_posts()[0].id.to_string();
+        set_deposit(accounts(2), 100);
+        for result in [Ok(()), Err(PromiseError::Failed), Ok(())] {
+            post.donate_author(post_id.clone(), U128::from(100), None);
+            post.on_donation_complete(post_id.clone(), accounts(2), U128::from(100), None, result);
+        }
+        //the failed donation isn't counted and goes back to the donor
+        assert_eq!(post.get_posts()[0].donation_amount, U128::from(200));
+        assert_eq!(post.get_donor_total(accounts(2)), U128::from(200));
+        let refunds: Vec<_> = get_created_receipts()
+            .into_iter()
+            .filter(|receipt| receipt.receiver_id == accounts(2))
+            .collect();
+        assert_eq!(refunds.len(), 1);
+        assert_eq!(refunds[0].actions, vec![VmAction::Transfer { deposit: 100 }]);
+    }
+
+    //test a failed transfer refunds the donor and records nothing
+    #[test]
+    pub fn failed_transfer_refunds_donor() {
+        let mut post = new_contract();
+        post.new_post("title".to_string(), "body".to_string(), None);
+        set_deposit(accounts(2), 100);
+        post.donate_author("0".to_string(), U128::from(100), None);
+        assert!(!post.on_donation_complete(
+            "0".to_string(),
+            accounts(2),
+            U128::from(100),
+            None,
+            Err(PromiseError::Failed)
+        ));
+        assert_eq!(post.get_donations("0".to_string()), Some(0));
+        let refund = get_created_receipts().pop().unwrap();
+        assert_eq!(refund.receiver_id, accounts(2));
+        assert_eq!(refund.actions, vec![VmAction::Transfer { deposit: 100 }]);
+    }
+
+    //test a post deleted before the callback is skipped
+    #[test]
+    pub fn donation_to_deleted_post() {
+        let mut post = new_contract_with_post();
+        set_deposit(accounts(2), 100);
+        post.donate_author("0".to_string(), U128::from(100), None);
+        set_caller(accounts(1));
+        post.delete_post("0".to_string());
+        assert!(post.on_donation_complete("0".to_string(), accounts(2), U128::from(100), None, Ok(())));
+        assert_eq!(post.get_donations("0".to_string()), None);
+    }
+
+    //test donating without a deposit
+    #[test]
+    #[should_panic(expected = "Attach a deposit to donate")]
+    pub fn donate_without_deposit() {
+        let mut post = new_contract();
+        post.new_post("title".to_string(), "body".to_string(), None);
+        post.donate_author("0".to_string(), U128::from(100), None);
+    }
+
+    //test claiming more than the attached deposit
+    #[test]
+    #[should_panic(expected = "Donation amount must equal the attached deposit")]
+    pub fn donate_more_than_deposit() {
+        let mut post = new_contract();
+        post.new_post("title".to_string(), "body".to_string(), None);
+        set_deposit(accounts(2), 100);
+        post.donate_author("0".to_string(), U128::from(1_000_000), None);
+    }
+
+    //test the deposit is refunded when the post doesn't exist
+    #[test]
+    pub fn donate_missing_post() {
+        let mut post = new_contract();
+        set_deposit(accounts(2), 100);
+        post.donate_author("0".to_string(), U128::from(100), None);
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, accounts(2));
+        assert_eq!(receipts[0].actions, vec![VmAction::Transfer { deposit: 100 }]);
+    }
+
+    //test donations are recorded per donor with their message
+    #[test]
+    pub fn donation_ledger() {
+        let mut post = new_contract_with_post();
+        post.new_post("title 1".to_string(), "body 1".to_string(), None);
+        let donations = [
+            ("0", accounts(2), 100, Some("thanks".to_string())),
+            ("0", accounts(3), 50, None),
+            ("1", accounts(2), 25, None),
+        ];
+        for (i, (post_id, donor, amount, message)) in donations.iter().enumerate() {
+            testing_env!(
+                VMContextBuilder::new()
+                    .predecessor_account_id(donor.clone())
+                    .attached_deposit(*amount)
+                    .block_timestamp(i as u64 + 1)
+                    .build()
+            );
+            post.donate_author(post_id.to_string(), U128::from(*amount), message.clone());
+            post.on_donation_complete(
+                post_id.to_string(),
+                donor.clone(),
+                U128::from(*amount),
+                message.clone(),
+                Ok(())
+            );
+        }
+
+        assert_eq!(post.get_post_donations("0".to_string(), None, None), vec![
+            Donation {
+                donor: accounts(2),
+                amount: U128::from(100),
+                timestamp: U64::from(1),
+                message: Some("thanks".to_string()),
+            },
+            Donation { donor: accounts(3), amount: U128::from(50), timestamp: U64::from(2), message: None }
+        ]);
+        let page = post.get_post_donations("0".to_string(), Some(U64::from(1)), Some(1));
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].donor, accounts(3));
+        assert!(post.get_post_donations("2".to_string(), None, None).is_empty());
+        let most_donated = post.list_posts(None, None, Some(SortOrder::MostDonated), None);
+        assert_eq!(most_donated[0].id, "0".to_string());
+        assert_eq!(post.get_donor_total(accounts(2)), U128::from(125));
+        assert_eq!(post.get_donor_total(accounts(3)), U128::from(50));
+        assert_eq!(post.get_author_total(accounts(1)), U128::from(175));
+        assert_eq!(post.get_author_total(accounts(2)), U128::from(0));
+    }
+
+    //test failed transfers stay out of the ledger
+    #[test]
+    pub fn failed_donation_not_recorded() {
+        let mut post = new_contract();
+        post.new_post("title".to_string(), "body".to_string(), None);
+        set_deposit(accounts(2), 100);
+        post.donate_author("0".to_string(), U128::from(100), None);
+        post.on_donation_complete("0".to_string(), accounts(2), U128::from(100), None, Err(PromiseError::Failed));
+        assert!(post.get_post_donations("0".to_string(), None, None).is_empty());
+        assert_eq!(post.get_donor_total(accounts(2)), U128::from(0));
+    }
+
+    //test ledger entries are charged to the donor and credited back once cleared
+    #[test]
+    pub fn donation_storage() {
+        let mut post = new_contract_with_post();
+        let available = |post: &Posts| post.storage_balance_of(accounts(2)).unwrap().available.0;
+        let registered = available(&post);
+        let message = "a".repeat(MAX_DONATION_MESSAGE_LEN);
+        set_deposit(accounts(2), 100);
+        post.donate_author("0".to_string(), U128::from(100), Some(message.clone()));
+        post.on_donation_complete("0".to_string(), accounts(2), U128::from(100), Some(message.clone()), Ok(()));
+        let charged = registered - available(&post);
+        assert!(charged > 0);
+        let entry_bytes = DONATION_ENTRY_BYTES + MAX_DONATION_MESSAGE_LEN as StorageUsage;
+        assert!(charged <= Balance::from(entry_bytes) * env::storage_byte_cost());
+
+        set_caller(accounts(1));
+        post.delete_post("0".to_string());
+        assert!(post.get_post_donations("0".to_string(), None, None).is_empty());
+        assert!(available(&post) < registered);
+        assert_eq!(post.clear_donations("0".to_string(), None), 0);
+        assert_eq!(available(&post), registered);
+        assert!(post.donations.get(&"0".to_string()).is_none());
+    }
+
+    //test the ledger of a deleted post is cleared in pages
+    #[test]
+    pub fn clear_donations_in_pages() {
+        let mut post = new_contract_with_post();
+        for donor in [accounts(2), accounts(3), accounts(2)] {
+            set_deposit(donor.clone(), 100);
+            post.donate_author("0".to_string(), U128::from(100), None);
+            post.on_donation_complete("0".to_string(), donor, U128::from(100), None, Ok(()));
+        }
+        set_caller(accounts(1));
+        post.delete_post("0".to_string());
+        assert_eq!(post.clear_donations("0".to_string(), Some(2)), 1);
+        assert_eq!(post.clear_donations("0".to_string(), Some(2)), 0);
+        assert_eq!(post.clear_donations("0".to_string(), Some(2)), 0);
+        //the totals stay
+        assert_eq!(post.get_donor_total(accounts(2)), U128::from(200));
+    }
+
+    //test donations to posts that still exist can't be cleared
+    #[test]
+    #[should_panic(expected = "Only donations to deleted posts can be cleared")]
+    pub fn clear_donations_live_post() {
+        let mut post = new_contract_with_post();
+        post.clear_donations("0".to_string(), None);
+    }
+
+    //test the reserved entry is handed back when nothing is recorded
+    #[test]
+    pub fn donation_reservation_refunded() {
+        let mut post = new_contract_with_post();
+        let available = |post: &Posts| post.storage_balance_of(accounts(2)).unwrap().available.0;
+        let registered = available(&post);
+        set_deposit(accounts(2), 100);
+        post.donate_author("0".to_string(), U128::from(100), None);
+        let reserved = Balance::from(DONATION_ENTRY_BYTES) * env::storage_byte_cost();
+        assert_eq!(available(&post), registered - reserved);
+        post.on_donation_complete("0".to_string(), accounts(2), U128::from(100), None, Err(PromiseError::Failed));
+        assert_eq!(available(&post), registered);
+
+        post.donate_author("0".to_string(), U128::from(100), None);
+        set_caller(accounts(1));
+        post.delete_post("0".to_string());
+        post.on_donation_complete("0".to_string(), accounts(2), U128::from(100), None, Ok(()));
+        assert_eq!(available(&post), registered);
+    }
+
+    //test the donation is recorded even if the donor withdrew everything meanwhile
+    #[test]
+    pub fn donation_after_withdraw() {
+        let mut post = new_contract_with_post();
+        set_deposit(accounts(2), 100);
+        post.donate_author("0".to_string(), U128::from(100), Some("thanks".to_string()));
+        set_deposit(accounts(2), 1);
+        post.storage_withdraw(None);
+        assert!(post.on_donation_complete(
+            "0".to_string(),
+            accounts(2),
+            U128::from(100),
+            Some("thanks".to_string()),
+            Ok(())
+        ));
+        assert_eq!(post.get_post_donations("0".to_string(), None, None).len(), 1);
+        assert!(post.storage_balance_of(accounts(2)).unwrap().available.0 > 0);
+    }
+
+    //test donors need the deposit for the ledger entry up front
+    #[test]
+    #[should_panic(expected = "is not registered, call storage_deposit first")]
+    pub fn donate_without_registration() {
+        let mut post = new_contract();
+        post.new_post("title".to_string(), "body".to_string(), None);
+        set_deposit("carol.near".parse().unwrap(), 100);
+        post.donate_author("0".to_string(), U128::from(100), None);
+    }
+
+    //test donation messages are capped
+    #[test]
+    #[should_panic(expected = "Donation message is too long")]
+    pub fn donate_with_long_message() {
+        let mut post = new_contract();
+        post.new_post("title".to_string(), "body".to_string(), None);
+        set_deposit(accounts(2), 100);
+        post.donate_author("0".to_string(), U128::from(100), Some("a".repeat(MAX_DONATION_MESSAGE_LEN + 1)));
+    }
+}
@@ -0,0 +1,164 @@
+use crate::*;
+
+//content of a post before one of its edits
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Revision {
+    pub title: String,
+    pub body: String,
+    pub image: Option<String>,
+    //block timestamp in nanoseconds of the edit that replaced this content
+    pub timestamp: U64,
+}
+
+#[near_bindgen]
+impl Posts {
+    //function to change the title, body and image of a post, author only, the post keeps
+    //its id, donations and reactions and the previous content goes to its revisions
+    pub fn edit_post(&mut self, post_id: String, title: String, body: String, image: Option<String>) {
+        let mut post = self.internal_get_post(&post_id).expect("Post not found");
+        require!(env::predecessor_account_id() == post.author, "Only the author can edit this post");
+        Self::assert_valid_image(&image);
+        let initial_usage = env::storage_usage();
+        let seq = self.post_seq.get(&post_id).expect("Post not found");
+        let now = U64::from(env::block_timestamp());
+
+        let mut revisions = self.revisions.get(&post_id).unwrap_or_else(|| {
+            Vector::new(StorageKey::PostRevisions { post_hash: env::sha256_array(post_id.as_bytes()) })
+        });
+        revisions.push(&Revision {
+            title: post.title.clone(),
+            body: post.body.clone(),
+            image: post.image.clone(),
+            timestamp: now,
+        });
+        self.revisions.insert(&post_id, &revisions);
+
+        self.internal_unindex_post(&post, seq);
+        let tags = extract_hashtags(&title, &body);
+        let mentions = self.internal_post_mentions(&post.author, &title, &body);
+        let removed_tags: Vec<String> = post.tags.iter().filter(|tag| !tags.contains(tag)).cloned().collect();
+        let added_tags: Vec<String> = tags.iter().filter(|tag| !post.tags.contains(tag)).cloned().collect();
+        let removed_mentions: Vec<AccountId> = post.mentions
+            .iter()
+            .filter(|account_id| !mentions.contains(account_id))
+            .cloned()
+            .collect();
+        let added_mentions: Vec<AccountId> = mentions
+            .iter()
+            .filter(|account_id| !post.mentions.contains(account_id))
+            .cloned()
+            .collect();
+
+        post.title = title;
+        post.body = body;
+        post.image = image;
+        post.tags = tags;
+        post.mentions = mentions;
+        post.edited_at = Some(now);
+        self.internal_index_post(&post, seq);
+        self.internal_unindex_tags(&removed_tags, seq);
+        self.internal_index_tags(&post_id, &added_tags, seq);
+        self.internal_unindex_mentions(&removed_mentions, seq);
+        self.internal_index_mentions(&post_id, &added_mentions, seq);
+        post.updated_at = Some(now);
+        self.internal_save_post(&post);
+        self.internal_charge_post_storage(&mut post, initial_usage);
+        Event::PostEdited(vec![PostEditedData { post_id: &post.id, author_id: &post.author }]).emit();
+        //only accounts the edit newly mentions are notified
+        Self::log_mentions(&post, &added_mentions);
+    }
+
+    //function to list the earlier versions of a post, oldest first
+    pub fn get_post_revisions(&self, post_id: String, from_index: Option<U64>, limit: Option<u64>) -> Vec<Revision> {
+        let (from_index, limit) = Self::page(from_index, limit);
+        match self.revisions.get(&post_id) {
+            Some(revisions) => revisions.iter().skip(from_index).take(limit).collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{ ids, new_contract, new_contract_with_post, set_caller };
+    use near_sdk::test_utils::{ accounts, get_logs, VMContextBuilder };
+    use near_sdk::testing_env;
+    //for testing purposes
+    use crate::IMAGE;
+
+    fn set_time(account_id: AccountId, timestamp: u64) {
+        testing_env!(VMContextBuilder::new().predecessor_account_id(account_id).block_timestamp(timestamp).build());
+    }
+
+    //test editing keeps the post and records the previous content
+    #[test]
+    pub fn edit_and_history() {
+        set_time(accounts(1), 100);
+        let mut post = new_contract();
+        post.new_post("titel".to_string(), "body".to_string(), None);
+        let mut stored = post.get_post("0".to_string()).unwrap();
+        stored.donation_amount = U128::from(500);
+        post.internal_save_post(&stored);
+        post.internal_rerank_post(&stored.id, 0, 500);
+        post.like_post("0".to_string());
+
+        set_time(accounts(1), 200);
+        post.edit_post("0".to_string(), "title".to_string(), "body".to_string(), Some(IMAGE.to_string()));
+        set_time(accounts(1), 300);
+        post.edit_post("0".to_string(), "title".to_string(), "new body".to_string(), None);
+
+        let edited = post.get_post("0".to_string()).unwrap();
+        assert_eq!(edited.title, "title".to_string());
+        assert_eq!(edited.body, "new body".to_string());
+        assert_eq!(edited.image, None);
+        assert_eq!(edited.donation_amount, U128::from(500));
+        assert_eq!(edited.reactions.get(&ReactionKind::Like), Some(&1));
+        assert_eq!(edited.edited_at, Some(U64::from(300)));
+        assert_eq!(edited.updated_at, Some(U64::from(300)));
+        assert_eq!(edited.created_at, U64::from(100));
+
+        let revisions = post.get_post_revisions("0".to_string(), None, None);
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0], Revision {
+            title: "titel".to_string(),
+            body: "body".to_string(),
+            image: None,
+            timestamp: U64::from(200),
+        });
+        assert_eq!(revisions[1].image, Some(IMAGE.to_string()));
+        assert_eq!(post.get_post_revisions("0".to_string(), Some(U64::from(1)), None).len(), 1);
+
+        //the history goes with the post
+        post.delete_post("0".to_string());
+        assert!(post.get_post_revisions("0".to_string(), None, None).is_empty());
+        assert!(post.revisions.get(&"0".to_string()).is_none());
+    }
+
+    //test the search, tag and mention indexes follow the new content
+    #[test]
+    pub fn edit_reindexes() {
+        set_caller(accounts(1));
+        let mut post = new_contract();
+        post.new_post("#old typo".to_string(), "hi @alice.near".to_string(), None);
+        post.edit_post("0".to_string(), "#new fixed".to_string(), "hi @carol.near".to_string(), None);
+
+        assert!(post.search_posts("typo".to_string(), None, None, None, None).is_empty());
+        assert_eq!(ids(&post.search_posts("fixed".to_string(), None, None, None, None)), vec!["0"]);
+        assert!(post.get_posts_by_tag("old".to_string(), None, None).is_empty());
+        assert_eq!(ids(&post.get_posts_by_tag("new".to_string(), None, None)), vec!["0"]);
+        assert!(post.get_mentions("alice.near".parse().unwrap(), None, None).is_empty());
+        assert_eq!(ids(&post.get_mentions("carol.near".parse().unwrap(), None, None)), vec!["0"]);
+        assert!(get_logs().last().unwrap().contains(r#""account_id":"carol.near""#));
+    }
+
+    //test only the author edits a post
+    #[test]
+    #[should_panic(expected = "Only the author can edit this post")]
+    pub fn edit_not_author() {
+        let mut post = new_contract_with_post();
+        set_caller(accounts(2));
+        post.edit_post("0".to_string(), "mine".to_string(), "body".to_string(), None);
+    }
+}
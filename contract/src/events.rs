@@ -0,0 +1,231 @@
+// NEP-297 events, logged as `EVENT_JSON:{"standard":"social_near","version":"1.0.0","event":...,"data":[...]}`
+use crate::*;
+
+pub const EVENT_STANDARD: &str = "social_near";
+pub const EVENT_VERSION: &str = "1.0.0";
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde", tag = "event", content = "data", rename_all = "snake_case")]
+pub enum Event<'a> {
+    PostCreated(Vec<PostCreatedData<'a>>),
+    PostDeleted(Vec<PostDeletedData<'a>>),
+    PostEdited(Vec<PostEditedData<'a>>),
+    Donation(Vec<DonationData<'a>>),
+    Follow(Vec<FollowData<'a>>),
+    Unfollow(Vec<FollowData<'a>>),
+    Comment(Vec<CommentData<'a>>),
+    Reaction(Vec<ReactionData<'a>>),
+    Mention(Vec<MentionData<'a>>),
+    RoleGranted(Vec<RoleData<'a>>),
+    RoleRevoked(Vec<RoleData<'a>>),
+    PostModerated(Vec<PostModeratedData<'a>>),
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PostCreatedData<'a> {
+    pub post_id: &'a str,
+    pub author_id: &'a AccountId,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PostDeletedData<'a> {
+    pub post_id: &'a str,
+    pub author_id: &'a AccountId,
+    pub deleted_by: &'a AccountId,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PostEditedData<'a> {
+    pub post_id: &'a str,
+    pub author_id: &'a AccountId,
+}
+
+//a donation in NEAR, or a tip in the fungible token `token_id`
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DonationData<'a> {
+    pub post_id: &'a str,
+    pub author_id: &'a AccountId,
+    pub donor_id: &'a AccountId,
+    pub amount: U128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<&'a AccountId>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FollowData<'a> {
+    pub follower_id: &'a AccountId,
+    pub account_id: &'a AccountId,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CommentData<'a> {
+    pub comment_id: &'a str,
+    pub post_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<&'a str>,
+    pub author_id: &'a AccountId,
+}
+
+//`reacted` is false when the reaction was taken back
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReactionData<'a> {
+    pub post_id: &'a str,
+    pub account_id: &'a AccountId,
+    pub kind: ReactionKind,
+    pub reacted: bool,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MentionData<'a> {
+    pub post_id: &'a str,
+    pub author_id: &'a AccountId,
+    pub account_id: &'a AccountId,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoleData<'a> {
+    pub account_id: &'a AccountId,
+    pub role: Role,
+    pub changed_by: &'a AccountId,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PostModeratedData<'a> {
+    pub post_id: &'a str,
+    pub action: ModerationAction,
+    pub moderator_id: &'a AccountId,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+}
+
+impl Event<'_> {
+    //the log line of the event
+    pub fn to_log(&self) -> String {
+        let log = EventLog { standard: EVENT_STANDARD, version: EVENT_VERSION, event: self };
+        format!("EVENT_JSON:{}", near_sdk::serde_json::to_string(&log).unwrap())
+    }
+
+    pub fn emit(&self) {
+        env::log_str(&self.to_log());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alice() -> AccountId {
+        "alice.near".parse().unwrap()
+    }
+
+    fn bob() -> AccountId {
+        "bob.near".parse().unwrap()
+    }
+
+    //test the post lifecycle events
+    #[test]
+    pub fn post_events() {
+        assert_eq!(
+            Event::PostCreated(vec![PostCreatedData { post_id: "0", author_id: &alice() }]).to_log(),
+            r#"EVENT_JSON:{"standard":"social_near","version":"1.0.0","event":"post_created","data":[{"post_id":"0","author_id":"alice.near"}]}"#
+        );
+        assert_eq!(
+            Event::PostDeleted(vec![PostDeletedData { post_id: "0", author_id: &alice(), deleted_by: &bob() }]).to_log(),
+            r#"EVENT_JSON:{"standard":"social_near","version":"1.0.0","event":"post_deleted","data":[{"post_id":"0","author_id":"alice.near","deleted_by":"bob.near"}]}"#
+        );
+        assert_eq!(
+            Event::PostEdited(vec![PostEditedData { post_id: "0", author_id: &alice() }]).to_log(),
+            r#"EVENT_JSON:{"standard":"social_near","version":"1.0.0","event":"post_edited","data":[{"post_id":"0","author_id":"alice.near"}]}"#
+        );
+        assert_eq!(
+            Event::PostModerated(vec![PostModeratedData { post_id: "0", action: ModerationAction::Hide, moderator_id: &bob() }]).to_log(),
+            r#"EVENT_JSON:{"standard":"social_near","version":"1.0.0","event":"post_moderated","data":[{"post_id":"0","action":"hide","moderator_id":"bob.near"}]}"#
+        );
+    }
+
+    //test donations in NEAR and in fungible tokens
+    #[test]
+    pub fn donation_events() {
+        let token: AccountId = "usdc.near".parse().unwrap();
+        assert_eq!(
+            Event::Donation(vec![DonationData {
+                post_id: "0",
+                author_id: &alice(),
+                donor_id: &bob(),
+                amount: U128::from(100),
+                token_id: None,
+            }]).to_log(),
+            r#"EVENT_JSON:{"standard":"social_near","version":"1.0.0","event":"donation","data":[{"post_id":"0","author_id":"alice.near","donor_id":"bob.near","amount":"100"}]}"#
+        );
+        assert_eq!(
+            Event::Donation(vec![DonationData {
+                post_id: "0",
+                author_id: &alice(),
+                donor_id: &bob(),
+                amount: U128::from(5),
+                token_id: Some(&token),
+            }]).to_log(),
+            r#"EVENT_JSON:{"standard":"social_near","version":"1.0.0","event":"donation","data":[{"post_id":"0","author_id":"alice.near","donor_id":"bob.near","amount":"5","token_id":"usdc.near"}]}"#
+        );
+    }
+
+    //test the social graph and interaction events
+    #[test]
+    pub fn interaction_events() {
+        assert_eq!(
+            Event::Follow(vec![FollowData { follower_id: &bob(), account_id: &alice() }]).to_log(),
+            r#"EVENT_JSON:{"standard":"social_near","version":"1.0.0","event":"follow","data":[{"follower_id":"bob.near","account_id":"alice.near"}]}"#
+        );
+        assert_eq!(
+            Event::Unfollow(vec![FollowData { follower_id: &bob(), account_id: &alice() }]).to_log(),
+            r#"EVENT_JSON:{"standard":"social_near","version":"1.0.0","event":"unfollow","data":[{"follower_id":"bob.near","account_id":"alice.near"}]}"#
+        );
+        assert_eq!(
+            Event::Comment(vec![CommentData { comment_id: "3", post_id: "0", parent_id: Some("1"), author_id: &bob() }]).to_log(),
+            r#"EVENT_JSON:{"standard":"social_near","version":"1.0.0","event":"comment","data":[{"comment_id":"3","post_id":"0","parent_id":"1","author_id":"bob.near"}]}"#
+        );
+        assert_eq!(
+            Event::Reaction(vec![ReactionData {
+                post_id: "0",
+                account_id: &bob(),
+                kind: ReactionKind::Laugh,
+                reacted: false,
+            }]).to_log(),
+            r#"EVENT_JSON:{"standard":"social_near","version":"1.0.0","event":"reaction","data":[{"post_id":"0","account_id":"bob.near","kind":"laugh","reacted":false}]}"#
+        );
+        assert_eq!(
+            Event::Mention(vec![MentionData { post_id: "0", author_id: &bob(), account_id: &alice() }]).to_log(),
+            r#"EVENT_JSON:{"standard":"social_near","version":"1.0.0","event":"mention","data":[{"post_id":"0","author_id":"bob.near","account_id":"alice.near"}]}"#
+        );
+    }
+
+    //test role changes
+    #[test]
+    pub fn role_events() {
+        assert_eq!(
+            Event::RoleGranted(vec![RoleData { account_id: &bob(), role: Role::Admin, changed_by: &alice() }]).to_log(),
+            r#"EVENT_JSON:{"standard":"social_near","version":"1.0.0","event":"role_granted","data":[{"account_id":"bob.near","role":"admin","changed_by":"alice.near"}]}"#
+        );
+        assert_eq!(
+            Event::RoleRevoked(vec![RoleData { account_id: &alice(), role: Role::Owner, changed_by: &alice() }]).to_log(),
+            r#"EVENT_JSON:{"standard":"social_near","version":"1.0.0","event":"role_revoked","data":[{"account_id":"alice.near","role":"owner","changed_by":"alice.near"}]}"#
+        );
+    }
+}
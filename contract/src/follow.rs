@@ -0,0 +1,287 @@
+use crate::*;
+use std::collections::BinaryHeap;
+
+//most accounts one account can follow, `get_feed` reads the posts of each of them
+pub const MAX_FOLLOWING: u64 = 200;
+
+//a page of the home feed, pass `next_cursor` back to get the following page
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Feed {
+    pub posts: Vec<Post>,
+    //None once the feed has no older posts
+    pub next_cursor: Option<U64>,
+}
+
+#[near_bindgen]
+impl Posts {
+    //function to follow an account, both sides of the edge are charged to the follower
+    //except the record of the followed account's set, which all its followers share
+    pub fn follow(&mut self, account_id: AccountId) {
+        let follower = env::predecessor_account_id();
+        require!(follower != account_id, "Can't follow yourself");
+        let initial_usage = env::storage_usage();
+        let mut following = Self::internal_account_set(&self.following, &follower, "following");
+        if !following.insert(&account_id) {
+            return;
+        }
+        require!(following.len() <= MAX_FOLLOWING, &format!("Can't follow more than {} accounts", MAX_FOLLOWING));
+        self.following.insert(&follower, &following);
+        let mut followers = Self::internal_account_set(&self.followers, &account_id, "followers");
+        followers.insert(&follower);
+        self.internal_charge_storage(&follower, initial_usage);
+        self.followers.insert(&account_id, &followers);
+        Event::Follow(vec![FollowData { follower_id: &follower, account_id: &account_id }]).emit();
+    }
+
+    //function to stop following an account
+    pub fn unfollow(&mut self, account_id: AccountId) {
+        let follower = env::predecessor_account_id();
+        let initial_usage = env::storage_usage();
+        if !Self::internal_remove_edge(&mut self.following, &follower, &account_id) {
+            return;
+        }
+        if let Some(mut followers) = self.followers.get(&account_id) {
+            followers.remove(&follower);
+            self.internal_charge_storage(&follower, initial_usage);
+            if followers.is_empty() {
+                self.followers.remove(&account_id);
+            } else {
+                self.followers.insert(&account_id, &followers);
+            }
+        }
+        Event::Unfollow(vec![FollowData { follower_id: &follower, account_id: &account_id }]).emit();
+    }
+
+    pub fn get_follower_count(&self, account_id: AccountId) -> u64 {
+        self.followers.get(&account_id).map(|followers| followers.len()).unwrap_or(0)
+    }
+
+    pub fn get_following_count(&self, account_id: AccountId) -> u64 {
+        self.following.get(&account_id).map(|following| following.len()).unwrap_or(0)
+    }
+
+    //function to list the accounts following `account_id`
+    pub fn get_followers(&self, account_id: AccountId, from_index: Option<U64>, limit: Option<u64>) -> Vec<AccountId> {
+        Self::internal_page_set(self.followers.get(&account_id), from_index, limit)
+    }
+
+    //function to list the accounts `account_id` follows
+    pub fn get_following(&self, account_id: AccountId, from_index: Option<U64>, limit: Option<u64>) -> Vec<AccountId> {
+        Self::internal_page_set(self.following.get(&account_id), from_index, limit)
+    }
+
+    //function to get the posts of the accounts `account_id` follows and hasn't muted, newest
+    //first and without hidden posts, merged from each author's own posts so it costs the same however many posts exist,
+    //following is capped at MAX_FOLLOWING accounts to keep the merge within the gas of a view
+    pub fn get_feed(&self, account_id: AccountId, cursor: Option<U64>, limit: Option<u64>) -> Feed {
+        let (_, limit) = Self::page(None, limit);
+        let cursor = cursor.map(|cursor| cursor.0).unwrap_or(u64::MAX);
+        let authors: Vec<TreeMap<u64, String>> = match self.following.get(&account_id) {
+            Some(following) => following
+                .iter()
+                .filter(|author| !self.internal_is_muted(&account_id, author))
+                .filter_map(|author| self.author_posts.get(&author))
+                .collect(),
+            None => Vec::new(),
+        };
+        let mut sources: Vec<_> = authors.iter().map(|posts| posts.iter_rev_from(cursor)).collect();
+        //newest post of every author not yet taken
+        let mut heads = BinaryHeap::new();
+        for (source, posts) in sources.iter_mut().enumerate() {
+            if let Some((seq, post_id)) = posts.next() {
+                heads.push((seq, source, post_id));
+            }
+        }
+        let mut posts = Vec::new();
+        let mut last_seq = cursor;
+        while posts.len() < limit {
+            let (seq, source, post_id) = match heads.pop() {
+                Some(head) => head,
+                None => break,
+            };
+            if let Some(post) = self.internal_get_post(&post_id).filter(|post| !post.hidden) {
+                posts.push(post);
+            }
+            last_seq = seq;
+            if let Some((seq, post_id)) = sources[source].next() {
+                heads.push((seq, source, post_id));
+            }
+        }
+        let next_cursor = if heads.is_empty() { None } else { Some(U64::from(last_seq)) };
+        Feed { posts, next_cursor }
+    }
+}
+
+impl Posts {
+    //set of accounts `account_id` points to in one of the account graphs, `kind` names
+    //the graph so each one gets its own storage prefix
+    pub(crate) fn internal_account_set(
+        edges: &LookupMap<AccountId, UnorderedSet<AccountId>>,
+        account_id: &AccountId,
+        kind: &str
+    ) -> UnorderedSet<AccountId> {
+        edges.get(account_id).unwrap_or_else(|| {
+            let account_hash = env::sha256_array(format!("{}:{}", kind, account_id).as_bytes());
+            UnorderedSet::new(StorageKey::AccountSet { account_hash })
+        })
+    }
+
+    //removes `to` from the set of `from`, returns whether it was there
+    pub(crate) fn internal_remove_edge(
+        edges: &mut LookupMap<AccountId, UnorderedSet<AccountId>>,
+        from: &AccountId,
+        to: &AccountId
+    ) -> bool {
+        let mut set = match edges.get(from) {
+            Some(set) => set,
+            None => return false,
+        };
+        if !set.remove(to) {
+            return false;
+        }
+        if set.is_empty() {
+            edges.remove(from);
+        } else {
+            edges.insert(from, &set);
+        }
+        true
+    }
+
+    pub(crate) fn internal_page_set(
+        set: Option<UnorderedSet<AccountId>>,
+        from_index: Option<U64>,
+        limit: Option<u64>
+    ) -> Vec<AccountId> {
+        let (from_index, limit) = Self::page(from_index, limit);
+        match set {
+            Some(set) => set.iter().skip(from_index).take(limit).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    //adds a post to its author's posts, which the feed is merged from
+    pub(crate) fn internal_index_author_post(&mut self, post: &Post, seq: u64) {
+        let mut post_ids = self.author_posts.get(&post.author).unwrap_or_else(|| {
+            TreeMap::new(StorageKey::AuthorPostsPerAccount {
+                account_hash: env::sha256_array(post.author.as_bytes()),
+            })
+        });
+        post_ids.insert(&seq, &post.id);
+        self.author_posts.insert(&post.author, &post_ids);
+    }
+
+    pub(crate) fn internal_unindex_author_post(&mut self, post: &Post, seq: u64) {
+        if let Some(mut post_ids) = self.author_posts.get(&post.author) {
+            post_ids.remove(&seq);
+            if post_ids.is_empty() {
+                self.author_posts.remove(&post.author);
+            } else {
+                self.author_posts.insert(&post.author, &post_ids);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{ ids, new_contract, set_caller };
+    use near_sdk::test_utils::accounts;
+
+    fn new_post(post: &mut Posts, author: AccountId) {
+        set_caller(author);
+        post.new_post("title".to_string(), "body".to_string(), None);
+    }
+
+    //test following and unfollowing updates both sides
+    #[test]
+    pub fn follow_and_unfollow() {
+        set_caller(accounts(1));
+        let mut post = new_contract();
+        post.follow(accounts(2));
+        post.follow(accounts(3));
+        post.follow(accounts(2));
+        set_caller(accounts(3));
+        post.follow(accounts(2));
+
+        assert_eq!(post.get_following_count(accounts(1)), 2);
+        assert_eq!(post.get_follower_count(accounts(2)), 2);
+        assert_eq!(post.get_following(accounts(1), None, None), vec![accounts(2), accounts(3)]);
+        assert_eq!(post.get_followers(accounts(2), Some(U64::from(1)), Some(1)), vec![accounts(3)]);
+
+        set_caller(accounts(1));
+        post.unfollow(accounts(2));
+        post.unfollow(accounts(4));
+        assert_eq!(post.get_following(accounts(1), None, None), vec![accounts(3)]);
+        assert_eq!(post.get_followers(accounts(2), None, None), vec![accounts(3)]);
+        set_caller(accounts(3));
+        post.unfollow(accounts(2));
+        assert_eq!(post.get_follower_count(accounts(2)), 0);
+        assert!(post.followers.get(&accounts(2)).is_none());
+    }
+
+    //test follow events are only logged when the graph changes
+    #[test]
+    pub fn follow_events() {
+        set_caller(accounts(1));
+        let mut post = new_contract();
+        post.follow(accounts(2));
+        post.follow(accounts(2));
+        post.unfollow(accounts(2));
+        post.unfollow(accounts(2));
+        let (follower_id, account_id) = (&accounts(1), &accounts(2));
+        assert_eq!(near_sdk::test_utils::get_logs(), vec![
+            Event::Follow(vec![FollowData { follower_id, account_id }]).to_log(),
+            Event::Unfollow(vec![FollowData { follower_id, account_id }]).to_log()
+        ]);
+    }
+
+    //test following yourself
+    #[test]
+    #[should_panic(expected = "Can't follow yourself")]
+    pub fn follow_yourself() {
+        set_caller(accounts(1));
+        let mut post = new_contract();
+        post.follow(accounts(1));
+    }
+
+    //test following is capped
+    #[test]
+    #[should_panic(expected = "Can't follow more than 200 accounts")]
+    pub fn follow_too_many() {
+        set_caller(accounts(1));
+        let mut post = new_contract();
+        for i in 0..MAX_FOLLOWING {
+            //a fresh context per call keeps the follow events under the log limit
+            set_caller(accounts(1));
+            post.follow(format!("account{}.near", i).parse().unwrap());
+        }
+        assert_eq!(post.get_following_count(accounts(1)), MAX_FOLLOWING);
+        post.follow(accounts(2));
+    }
+
+    //test the feed merges followed authors newest first and pages with the cursor
+    #[test]
+    pub fn home_feed() {
+        let mut post = new_contract();
+        for author in [2, 3, 4, 2, 3, 2] {
+            new_post(&mut post, accounts(author));
+        }
+        set_caller(accounts(1));
+        assert_eq!(post.get_feed(accounts(1), None, None), Feed { posts: Vec::new(), next_cursor: None });
+        post.follow(accounts(2));
+        post.follow(accounts(3));
+
+        let page = post.get_feed(accounts(1), None, Some(3));
+        assert_eq!(ids(&page.posts), vec!["5", "4", "3"]);
+        assert_eq!(page.next_cursor, Some(U64::from(3)));
+        let page = post.get_feed(accounts(1), page.next_cursor, Some(3));
+        assert_eq!(ids(&page.posts), vec!["1", "0"]);
+        assert_eq!(page.next_cursor, None);
+
+        set_caller(accounts(2));
+        post.delete_post("5".to_string());
+        assert_eq!(ids(&post.get_feed(accounts(1), None, Some(2)).posts), vec!["4", "3"]);
+    }
+}
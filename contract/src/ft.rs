@@ -0,0 +1,200 @@
+use crate::*;
+use near_contract_standards::fungible_token::core::ext_ft_core;
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+
+//gas for forwarding the tip to the author and for `on_tip_complete`
+const GAS_FOR_FT_TRANSFER: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_TIP_CALLBACK: Gas = Gas(10_000_000_000_000);
+
+//`msg` expected with `ft_transfer_call`, e.g. {"post_id": "0"}
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TipMessage {
+    pub post_id: String,
+}
+
+#[near_bindgen]
+impl FungibleTokenReceiver for Posts {
+    //function to tip the author of a post in an accepted fungible token,
+    //the tokens are forwarded to the author and refunded if that fails
+    fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        let token_id = env::predecessor_account_id();
+        require!(self.accepted_tokens.contains(&token_id), "Token is not accepted for tips");
+        require!(amount.0 > 0, "Tip amount must be positive");
+        let tip: TipMessage = near_sdk::serde_json::from_str(&msg).expect(
+            "msg must be a JSON object with the post_id to tip"
+        );
+        let post = self.internal_get_post(&tip.post_id).expect("Post not found");
+        self.assert_not_blocked(&post, &sender_id);
+
+        ext_ft_core::ext(token_id.clone())
+            .with_attached_deposit(1)
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(post.author, amount, Some(format!("Tip for post {}", post.id)))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_TIP_CALLBACK)
+                    .on_tip_complete(post.id, token_id, sender_id, amount)
+            )
+            .into()
+    }
+}
+
+#[near_bindgen]
+impl Posts {
+    //callback of the transfer to the author, returns the amount the token contract
+    //should refund to the sender, so a failed transfer gives everything back
+    #[private]
+    pub fn on_tip_complete(
+        &mut self,
+        post_id: String,
+        token_id: AccountId,
+        sender_id: AccountId,
+        amount: U128,
+        #[callback_result] result: Result<(), PromiseError>
+    ) -> U128 {
+        if result.is_err() {
+            env::log_str(&format!("Tip to post '{}' failed, refunding {}", post_id, sender_id));
+            return amount;
+        }
+        match self.internal_get_post(&post_id) {
+            Some(mut post) => {
+                let total = post.token_donations.get(&token_id).map(|total| total.0).unwrap_or(0);
+                post.token_donations.insert(token_id.clone(), U128::from(total + amount.0));
+                Event::Donation(vec![DonationData {
+                    post_id: &post.id,
+                    author_id: &post.author,
+                    donor_id: &sender_id,
+                    amount,
+                    token_id: Some(&token_id),
+                }]).emit();
+                self.internal_update_post(post);
+            }
+            None => {
+                //deleted while the transfer was in flight, the author still got paid
+                env::log_str(&format!("Couldn't find post '{}' to record the tip", post_id));
+            }
+        }
+        U128::from(0)
+    }
+
+    //function to accept tips in a fungible token, admin only
+    pub fn add_accepted_token(&mut self, token_id: AccountId) {
+        self.assert_role(Role::Admin);
+        self.accepted_tokens.insert(&token_id);
+    }
+
+    //function to stop accepting tips in a fungible token, admin only
+    pub fn remove_accepted_token(&mut self, token_id: AccountId) {
+        self.assert_role(Role::Admin);
+        self.accepted_tokens.remove(&token_id);
+    }
+
+    pub fn get_accepted_tokens(&self) -> Vec<AccountId> {
+        self.accepted_tokens.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{ new_contract_with_post, set_caller };
+    use near_sdk::mock::VmAction;
+    use near_sdk::test_utils::{ accounts, get_created_receipts };
+
+    fn token() -> AccountId {
+        "usdc.near".parse().unwrap()
+    }
+
+    //contract owned by accounts(0) that accepts `token()`, with a post by accounts(1)
+    fn setup() -> Posts {
+        let mut post = new_contract_with_post();
+        set_caller(accounts(0));
+        post.add_accepted_token(token());
+        set_caller(accounts(1));
+        post
+    }
+
+    fn tip(post: &mut Posts, amount: u128, msg: &str) -> PromiseOrValue<U128> {
+        set_caller(token());
+        post.ft_on_transfer(accounts(2), U128::from(amount), msg.to_string())
+    }
+
+    //test a tip is forwarded to the author and totalled per token
+    #[test]
+    pub fn tip_author() {
+        let mut post = setup();
+        assert!(matches!(tip(&mut post, 500, r#"{"post_id":"0"}"#), PromiseOrValue::Promise(_)));
+        let receipts = get_created_receipts();
+        assert_eq!(receipts[0].receiver_id, token());
+        match &receipts[0].actions[0] {
+            VmAction::FunctionCall { function_name, args, deposit, .. } => {
+                assert_eq!(function_name, "ft_transfer");
+                assert_eq!(*deposit, 1);
+                let args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(args).unwrap();
+                assert_eq!(args["receiver_id"], accounts(1).to_string());
+                assert_eq!(args["amount"], "500");
+            }
+            action => panic!("unexpected action {:?}", action),
+        }
+
+        set_caller(env::current_account_id());
+        let unused = post.on_tip_complete("0".to_string(), token(), accounts(2), U128::from(500), Ok(()));
+        assert_eq!(unused, U128::from(0));
+        post.on_tip_complete("0".to_string(), token(), accounts(2), U128::from(250), Ok(()));
+        let stored = post.get_posts().remove(0);
+        assert_eq!(stored.token_donations.get(&token()), Some(&U128::from(750)));
+        assert_eq!(stored.donation_amount, U128::from(0));
+    }
+
+    //test a failed forward hands the tokens back to the sender
+    #[test]
+    pub fn failed_tip_is_refunded() {
+        let mut post = setup();
+        tip(&mut post, 500, r#"{"post_id":"0"}"#);
+        let unused = post.on_tip_complete(
+            "0".to_string(),
+            token(),
+            accounts(2),
+            U128::from(500),
+            Err(PromiseError::Failed)
+        );
+        assert_eq!(unused, U128::from(500));
+        assert!(post.get_posts()[0].token_donations.is_empty());
+    }
+
+    //test tips in tokens that aren't on the allowlist
+    #[test]
+    #[should_panic(expected = "Token is not accepted for tips")]
+    pub fn tip_with_unknown_token() {
+        let mut post = setup();
+        set_caller(accounts(0));
+        post.remove_accepted_token(token());
+        assert!(post.get_accepted_tokens().is_empty());
+        tip(&mut post, 500, r#"{"post_id":"0"}"#);
+    }
+
+    //test tips to posts that don't exist
+    #[test]
+    #[should_panic(expected = "Post not found")]
+    pub fn tip_missing_post() {
+        let mut post = setup();
+        tip(&mut post, 500, r#"{"post_id":"7"}"#);
+    }
+
+    //test tips without a post id
+    #[test]
+    #[should_panic(expected = "msg must be a JSON object with the post_id to tip")]
+    pub fn tip_without_post_id() {
+        let mut post = setup();
+        tip(&mut post, 500, "0");
+    }
+
+    //test only admins edit the allowlist
+    #[test]
+    #[should_panic(expected = "Only an admin can call this method")]
+    pub fn add_accepted_token_not_admin() {
+        let mut post = setup();
+        post.add_accepted_token(accounts(3));
+    }
+}
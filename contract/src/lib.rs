@@ -1,15 +1,75 @@
-use std::u8;
 // Find all our documentation at https://docs.near.org
 use near_sdk::borsh::{ self, BorshDeserialize, BorshSerialize };
-use near_sdk::{ near_bindgen, AccountId, env, Balance, Promise };
+use near_sdk::collections::{ LookupMap, TreeMap, UnorderedMap, UnorderedSet, Vector };
+use near_sdk::{
+    near_bindgen,
+    require,
+    AccountId,
+    env,
+    Balance,
+    BorshStorageKey,
+    PanicOnDefault,
+    CryptoHash,
+    Gas,
+    Promise,
+    PromiseError,
+    PromiseOrValue,
+    StorageUsage,
+};
 use near_sdk::serde::{ Serialize, Deserialize };
-use uuid::Uuid;
-use near_sdk::json_types::U128;
-use ipfs_api::{ IpfsClient, IpfsApi };
-use std::io::Cursor;
+use near_sdk::json_types::{ U128, U64 };
+use std::collections::BTreeMap;
 
-//for testing purpose 
-const IMAGE: &str = "https://static.vecteezy.com/packs/media/vectors/term-bg-1-3d6355ab.jpg";
+pub use crate::comments::*;
+pub use crate::donation::*;
+pub use crate::edit::*;
+pub use crate::events::*;
+pub use crate::follow::*;
+pub use crate::ft::*;
+pub use crate::mentions::*;
+pub use crate::migrate::*;
+pub use crate::moderation::*;
+pub use crate::profile::*;
+pub use crate::reactions::*;
+pub use crate::roles::*;
+pub use crate::search::*;
+pub use crate::storage::*;
+pub use crate::tags::*;
+
+mod block;
+mod cid;
+mod comments;
+mod donation;
+mod edit;
+mod events;
+mod follow;
+mod ft;
+mod mentions;
+mod migrate;
+mod moderation;
+mod profile;
+mod reactions;
+mod roles;
+mod search;
+mod storage;
+mod tags;
+
+//for testing purpose
+#[cfg(test)]
+const IMAGE: &str = "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o";
+
+//page size of paginated views when no limit is given, and the largest one allowed
+pub const DEFAULT_PAGE_SIZE: u64 = 20;
+pub const MAX_PAGE_SIZE: u64 = 100;
+
+//order of the posts returned by `list_posts`
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum SortOrder {
+    Newest,
+    Oldest,
+    MostDonated,
+}
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct Post {
@@ -17,146 +77,483 @@ pub struct Post {
     pub author: AccountId, //account id
     pub title: String,
     pub body: String,
-    //add image variable as optional parameter
+    //IPFS CID of the image, uploaded off chain by the client
     pub image: Option<String>,
     //add donation information
     pub donation_amount: U128,
+    //fungible token contract -> total tipped in that token
+    pub token_donations: BTreeMap<AccountId, U128>,
+    //lowercase #hashtags found in the title and body
+    pub tags: Vec<String>,
+    //valid accounts @mentioned in the title and body
+    pub mentions: Vec<AccountId>,
+    //number of accounts that left each kind of reaction
+    pub reactions: BTreeMap<ReactionKind, u64>,
+    //block timestamp in nanoseconds of the last edit, None if never edited
+    pub edited_at: Option<U64>,
+    //block timestamp in nanoseconds and block height the post was created at,
+    //UNKNOWN_CREATION for posts migrated from before they were recorded
+    pub created_at: U64,
+    pub block_height: U64,
+    //block timestamp in nanoseconds of the last change to the stored post, None if never changed
+    pub updated_at: Option<U64>,
+    //hidden by a moderator, left out of `list_posts` and `search_posts` but still returned by `get_post`
+    pub hidden: bool,
+    //bytes charged to the author for the post, its indexes and revisions, refunded as is on delete
+    #[serde(skip)]
+    pub storage_bytes: StorageUsage,
+}
+
+//prefixes of the persistent collections
+#[derive(BorshSerialize, BorshStorageKey)]
+pub enum StorageKey {
+    Posts,
+    PostIndex,
+    PostSeq,
+    Moderators,
+    Donations,
+    PostDonations { post_hash: CryptoHash },
+    DonorTotals,
+    AuthorTotals,
+    AcceptedTokens,
+    DonationRank,
+    SearchIndex,
+    SearchToken { token_hash: CryptoHash },
+    TagPosts,
+    TagPostsPerTag { tag_hash: CryptoHash },
+    Mentions,
+    MentionsPerAccount { account_hash: CryptoHash },
+    Comments,
+    CommentThreads,
+    CommentThread { parent_hash: CryptoHash },
+    Reactions,
+    Reactors { key_hash: CryptoHash },
+    Profiles,
+    Following,
+    Followers,
+    AccountSet { account_hash: CryptoHash },
+    AuthorPosts,
+    AuthorPostsPerAccount { account_hash: CryptoHash },
+    Blocked,
+    Muted,
+    Revisions,
+    PostRevisions { post_hash: CryptoHash },
+    TimeIndex,
+    StorageAccounts,
+    Admins,
+    Reports,
+    PostReports,
+    PostReporters { post_hash: CryptoHash },
+    AuditLog,
 }
 
 #[near_bindgen]
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Posts {
-    pub posts: Vec<Post>,
+    //post id -> post, stored versioned so the layout can change
+    pub posts: LookupMap<String, VersionedPost>,
+    //insertion order -> post id
+    pub post_index: TreeMap<u64, String>,
+    //post id -> insertion order, used to drop the post from the indexes
+    pub post_seq: LookupMap<String, u64>,
+    //(donation amount, insertion order) -> post id
+    pub donation_rank: TreeMap<(Balance, u64), String>,
+    //insertion order of the next post, also used as its id
+    pub next_seq: u64,
+    pub owner_id: AccountId,
+    //accounts allowed to delete any post and hide any comment
+    pub moderators: UnorderedSet<AccountId>,
+    //post id -> every donation made to it, oldest first
+    pub donations: LookupMap<String, Vector<Donation>>,
+    //account -> total it has donated
+    pub donor_totals: LookupMap<AccountId, Balance>,
+    //account -> total it has received across all of its posts
+    pub author_totals: LookupMap<AccountId, Balance>,
+    //fungible token contracts accepted by `ft_on_transfer`
+    pub accepted_tokens: UnorderedSet<AccountId>,
+    //search token -> (insertion order -> post id) of the posts containing it
+    pub search_index: LookupMap<String, TreeMap<u64, String>>,
+    //hashtag -> (insertion order -> post id) of the posts using it
+    pub tag_posts: LookupMap<String, TreeMap<u64, String>>,
+    //account -> (insertion order -> post id) of the posts mentioning it
+    pub mentions: LookupMap<AccountId, TreeMap<u64, String>>,
+    //comment id -> comment
+    pub comments: LookupMap<String, Comment>,
+    //parent comment id, or "post:<post id>" for top level comments -> (insertion order -> comment id)
+    pub comment_threads: LookupMap<String, TreeMap<u64, String>>,
+    //insertion order of the next comment, also used as its id
+    pub next_comment_seq: u64,
+    //(post id, reaction) -> accounts that reacted that way -> bytes they were charged for it
+    pub reactions: LookupMap<(String, ReactionKind), UnorderedMap<AccountId, StorageUsage>>,
+    pub profiles: LookupMap<AccountId, Profile>,
+    //account -> accounts it follows
+    pub following: LookupMap<AccountId, UnorderedSet<AccountId>>,
+    //account -> accounts following it
+    pub followers: LookupMap<AccountId, UnorderedSet<AccountId>>,
+    //author -> (insertion order -> post id) of their posts
+    pub author_posts: LookupMap<AccountId, TreeMap<u64, String>>,
+    //account -> accounts it blocked
+    pub blocked: LookupMap<AccountId, UnorderedSet<AccountId>>,
+    //account -> accounts it muted
+    pub muted: LookupMap<AccountId, UnorderedSet<AccountId>>,
+    //post id -> earlier versions of the post, oldest first
+    pub revisions: LookupMap<String, Vector<Revision>>,
+    //(creation timestamp, insertion order) -> post id
+    pub time_index: TreeMap<(u64, u64), String>,
+    //account -> its storage deposit and the bytes charged to it
+    pub storage_accounts: LookupMap<AccountId, StorageAccount>,
+    //bytes of a registration, the minimum storage balance
+    pub registration_bytes: StorageUsage,
+    //accounts allowed to manage moderators and the configuration
+    pub admins: UnorderedSet<AccountId>,
+    //insertion order -> open report, the moderation queue
+    pub reports: TreeMap<u64, Report>,
+    //post id -> (reporter -> insertion order) of its open reports
+    pub post_reports: LookupMap<String, UnorderedMap<AccountId, u64>>,
+    //insertion order of the next report, also used as its id
+    pub next_report_seq: u64,
+    //the last MAX_AUDIT_ENTRIES moderation actions, the oldest gets overwritten by the next one
+    pub audit_log: Vector<AuditEntry>,
+    //moderation actions logged so far, the next one goes to this index modulo MAX_AUDIT_ENTRIES
+    pub next_audit_seq: u64,
 }
 
 #[near_bindgen]
 impl Posts {
-    pub fn new() -> Self {
-        Self {
-            posts: Vec::new(),
-        }
+    #[init]
+    pub fn new(owner_id: AccountId) -> Self {
+        let mut this = Self {
+            posts: LookupMap::new(StorageKey::Posts),
+            post_index: TreeMap::new(StorageKey::PostIndex),
+            post_seq: LookupMap::new(StorageKey::PostSeq),
+            donation_rank: TreeMap::new(StorageKey::DonationRank),
+            next_seq: 0,
+            owner_id,
+            moderators: UnorderedSet::new(StorageKey::Moderators),
+            donations: LookupMap::new(StorageKey::Donations),
+            donor_totals: LookupMap::new(StorageKey::DonorTotals),
+            author_totals: LookupMap::new(StorageKey::AuthorTotals),
+            accepted_tokens: UnorderedSet::new(StorageKey::AcceptedTokens),
+            search_index: LookupMap::new(StorageKey::SearchIndex),
+            tag_posts: LookupMap::new(StorageKey::TagPosts),
+            mentions: LookupMap::new(StorageKey::Mentions),
+            comments: LookupMap::new(StorageKey::Comments),
+            comment_threads: LookupMap::new(StorageKey::CommentThreads),
+            next_comment_seq: 0,
+            reactions: LookupMap::new(StorageKey::Reactions),
+            profiles: LookupMap::new(StorageKey::Profiles),
+            following: LookupMap::new(StorageKey::Following),
+            followers: LookupMap::new(StorageKey::Followers),
+            author_posts: LookupMap::new(StorageKey::AuthorPosts),
+            blocked: LookupMap::new(StorageKey::Blocked),
+            muted: LookupMap::new(StorageKey::Muted),
+            revisions: LookupMap::new(StorageKey::Revisions),
+            time_index: TreeMap::new(StorageKey::TimeIndex),
+            storage_accounts: LookupMap::new(StorageKey::StorageAccounts),
+            registration_bytes: 0,
+            admins: UnorderedSet::new(StorageKey::Admins),
+            reports: TreeMap::new(StorageKey::Reports),
+            post_reports: LookupMap::new(StorageKey::PostReports),
+            next_report_seq: 0,
+            audit_log: Vector::new(StorageKey::AuditLog),
+            next_audit_seq: 0,
+        };
+        this.measure_registration_bytes();
+        Self::write_state_version();
+        this
     }
 
-    //function to create a new post
+    //function to create a new post, `image` is the CID of an already uploaded file,
+    //its storage is charged to the author's storage deposit
     pub fn new_post(&mut self, title: String, body: String, image: Option<String>) {
-        let image_to_ipfs = write_image_to_ipfs(image.unwrap_or_default());
-        let image_hash = match image_to_ipfs {
-            Ok(hash) => Some(hash.clone()),
-            Err(err) => None
-        };
-        self.posts.push(Post {
-            id: Uuid::new_v4().to_string(),
-            author: env::predecessor_account_id(),
+        let initial_usage = env::storage_usage();
+        Self::assert_valid_image(&image);
+        let tags = extract_hashtags(&title, &body);
+        let author = env::predecessor_account_id();
+        let mentions = self.internal_post_mentions(&author, &title, &body);
+        let mut post = Post {
+            id: self.next_seq.to_string(),
+            author,
             title,
             body,
-            image: image_hash,
+            image,
             donation_amount: U128::from(0),
-        });
-        env::log_str("Post Created Successfully");
-        env::log_str(&format!("{:?}", self));
-
+            token_donations: BTreeMap::new(),
+            tags,
+            mentions,
+            reactions: BTreeMap::new(),
+            edited_at: None,
+            created_at: U64::from(env::block_timestamp()),
+            block_height: U64::from(env::block_height()),
+            updated_at: None,
+            hidden: false,
+            storage_bytes: 0,
+        };
+        self.internal_add_post(post.clone());
+        self.internal_charge_post_storage(&mut post, initial_usage);
+        Event::PostCreated(vec![PostCreatedData { post_id: &post.id, author_id: &post.author }]).emit();
+        Self::log_mentions(&post, &post.mentions);
     }
 
-    //function to get all posts
+    //function to get posts oldest first, kept for older clients and capped at
+    //MAX_PAGE_SIZE posts, use `list_posts` to page through everything
     pub fn get_posts(&self) -> Vec<Post> {
-        self.posts.clone()
+        self.list_posts(None, Some(MAX_PAGE_SIZE), Some(SortOrder::Oldest), None)
+    }
+
+    //function to get a page of posts, newest first unless another order is given, without
+    //hidden posts unless `include_hidden` is set, for moderators going through them
+    pub fn list_posts(
+        &self,
+        from_index: Option<U64>,
+        limit: Option<u64>,
+        sort: Option<SortOrder>,
+        include_hidden: Option<bool>
+    ) -> Vec<Post> {
+        let (from_index, limit) = Self::page(from_index, limit);
+        let include_hidden = include_hidden.unwrap_or(false);
+        let post_ids: Box<dyn Iterator<Item = String>> = match sort.unwrap_or(SortOrder::Newest) {
+            SortOrder::Newest => Box::new(self.post_index.iter_rev().map(|(_, id)| id)),
+            SortOrder::Oldest => Box::new(self.post_index.iter().map(|(_, id)| id)),
+            SortOrder::MostDonated => Box::new(self.donation_rank.iter_rev().map(|(_, id)| id)),
+        };
+        //hidden posts are left out before paging so pages stay full
+        post_ids
+            .filter_map(|post_id| self.internal_get_post(&post_id))
+            .filter(|post| include_hidden || !post.hidden)
+            .skip(from_index)
+            .take(limit)
+            .collect()
+    }
+
+    //function to get the posts created from `from_timestamp` (inclusive) until `to_timestamp`
+    //(exclusive), in nanoseconds, newest first, without hidden posts
+    pub fn get_posts_by_time(
+        &self,
+        from_timestamp: Option<U64>,
+        to_timestamp: Option<U64>,
+        from_index: Option<U64>,
+        limit: Option<u64>
+    ) -> Vec<Post> {
+        let (from_index, limit) = Self::page(from_index, limit);
+        let from_timestamp = from_timestamp.map(|timestamp| timestamp.0).unwrap_or(0);
+        let to_timestamp = to_timestamp.map(|timestamp| timestamp.0).unwrap_or(u64::MAX);
+        self.time_index
+            .iter_rev_from((to_timestamp, 0))
+            .take_while(|((created_at, _), _)| *created_at >= from_timestamp)
+            .filter_map(|(_, post_id)| self.internal_get_post(&post_id))
+            .filter(|post| !post.hidden)
+            .skip(from_index)
+            .take(limit)
+            .collect()
     }
 
-    //function to search for posts
-    pub fn search_posts(&self, search_string: String) -> Vec<Post> {
-        let search_result = self.posts
-            .iter()
-            .filter(|post| post.title.contains(&search_string))
-            .cloned()
-            .collect();
-        return search_result;
+    //function to get a single post
+    pub fn get_post(&self, post_id: String) -> Option<Post> {
+        self.internal_get_post(&post_id)
     }
 
-    //function to delete a post
+    //function to delete a post, allowed to its author and moderators
     pub fn delete_post(&mut self, post_id: String) {
-        self.posts.retain(|post| post.id != post_id);
-    }
-
-    //function to donate a author of the post
-    #[payable]
-    pub fn donate_author(&mut self, post_id: String, amount: U128) {
-        match self.posts.iter_mut().find(|post| post.id == post_id) {
-            Some(post) => {
-                let donor: AccountId = env::signer_account_id();
-                let receiver_accound_id = &post.author;
-                let amount_transfer: Balance = amount.into();
-                let mut total_donation: u128 = post.donation_amount.clone().into();
-                total_donation += amount_transfer;
-                Promise::new(receiver_accound_id.clone()).transfer(amount_transfer);
-                post.donation_amount = U128::from(total_donation);
-            }
-            None => {
-                // post not found
-                env::log_str(&format!("Couldn't find post '{}'", post_id));
-            }
+        let post = self.internal_get_post(&post_id).expect("Post not found");
+        let caller = env::predecessor_account_id();
+        require!(
+            caller == post.author || self.internal_has_role(&caller, Role::Moderator),
+            "Only the author or a moderator can delete this post"
+        );
+        self.internal_delete_post(post, &caller, None);
+    }
+}
+
+impl Posts {
+    //deletes a post and closes its reports, deletions by anyone but the author go to the audit log
+    pub(crate) fn internal_delete_post(&mut self, post: Post, deleted_by: &AccountId, note: Option<String>) {
+        self.internal_remove_post(&post.id);
+        self.internal_refund_bytes(&post.author, post.storage_bytes);
+        if deleted_by == &post.author {
+            self.internal_close_reports(&post.id);
+        } else {
+            self.internal_log_moderation(&post.id, ModerationAction::Remove, note);
         }
+        Event::PostDeleted(vec![PostDeletedData { post_id: &post.id, author_id: &post.author, deleted_by }]).emit();
     }
 
-    //function to get all donations from the post
-    pub fn get_donations(&mut self, post_id: String) -> Option<u128> {
-        match self.posts.iter().find(|post| post.id == post_id) {
-            Some(post) => Some(post.donation_amount.into()),
-            None => None,
+    pub(crate) fn assert_valid_image(image: &Option<String>) {
+        if let Some(cid) = image {
+            if let Err(reason) = cid::validate_cid(cid) {
+                panic!("Invalid image CID '{}': {}", cid, reason);
+            }
         }
     }
-}
 
-//function to write image into ipfs
-fn write_image_to_ipfs(image_url: String) -> Result<String, ipfs_api::Error> {
-    let client = IpfsClient::default();
-    let data = Cursor::new(image_url);
-    let res = client.add(data);
-    let res = tokio::runtime::Runtime::new().unwrap().block_on(res);
-    match res {
-        Ok(res) => Ok(res.hash),
-        Err(e) => Err(e)
+    //accounts mentioned in a post, without those that blocked its author
+    pub(crate) fn internal_post_mentions(&self, author: &AccountId, title: &str, body: &str) -> Vec<AccountId> {
+        let mut mentions = extract_mentions(title, body);
+        mentions.retain(|account_id| !self.internal_is_blocked(account_id, author));
+        mentions
     }
-}
 
+    //reads a post, upgrading it from whatever layout it was stored with
+    pub(crate) fn internal_get_post(&self, post_id: &String) -> Option<Post> {
+        self.posts.get(post_id).map(Post::from)
+    }
 
-#[cfg(test)]
-mod test_ipfs {
-    use std::io::Cursor;
-    use ipfs_api::{ IpfsClient, IpfsApi };
-    use crate::IMAGE;
-    //test write image to ipfs
-    #[test]
-    fn test_write_image_to_ipfs() {
-        let client = IpfsClient::default();
-        let data = Cursor::new(IMAGE.to_string());
-        let res = client.add(data);
-        let res = tokio::runtime::Runtime::new().unwrap().block_on(res);
-        match res {
-            Ok(res) => println!("Hash value: {}", res.hash),
-            Err(e) => println!("Error: {}", e),
+    //writes a post back in the current layout
+    pub(crate) fn internal_save_post(&mut self, post: &Post) {
+        self.posts.insert(&post.id, &VersionedPost::V2(post.clone()));
+    }
+
+    //writes back a post after a change, marking when it was updated
+    pub(crate) fn internal_update_post(&mut self, mut post: Post) {
+        post.updated_at = Some(U64::from(env::block_timestamp()));
+        self.internal_save_post(&post);
+    }
+
+    //stores a post and adds it to the ordered, search, tag, mention and author indexes
+    pub(crate) fn internal_add_post(&mut self, post: Post) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.post_index.insert(&seq, &post.id);
+        self.post_seq.insert(&post.id, &seq);
+        self.donation_rank.insert(&(post.donation_amount.0, seq), &post.id);
+        self.time_index.insert(&(post.created_at.0, seq), &post.id);
+        self.internal_index_post(&post, seq);
+        self.internal_index_tags(&post.id, &post.tags, seq);
+        self.internal_index_mentions(&post.id, &post.mentions, seq);
+        self.internal_index_author_post(&post, seq);
+        self.internal_save_post(&post);
+    }
+
+    //moves a post within the most donated order after its donation amount changed
+    pub(crate) fn internal_rerank_post(&mut self, post_id: &String, old_amount: Balance, new_amount: Balance) {
+        if let Some(seq) = self.post_seq.get(post_id) {
+            self.donation_rank.remove(&(old_amount, seq));
+            self.donation_rank.insert(&(new_amount, seq), post_id);
         }
     }
 
+    //range of a paginated view, `limit` is capped at MAX_PAGE_SIZE
+    pub(crate) fn page(from_index: Option<U64>, limit: Option<u64>) -> (usize, usize) {
+        let from_index = from_index.map(|index| index.0).unwrap_or(0);
+        let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+        (from_index as usize, limit as usize)
+    }
+
+    //removes a post, its index entries and its revisions, returning the removed post
+    pub(crate) fn internal_remove_post(&mut self, post_id: &String) -> Option<Post> {
+        let post = self.posts.remove(post_id).map(Post::from)?;
+        if let Some(seq) = self.post_seq.remove(post_id) {
+            self.post_index.remove(&seq);
+            self.donation_rank.remove(&(post.donation_amount.0, seq));
+            self.time_index.remove(&(post.created_at.0, seq));
+            self.internal_unindex_tags(&post.tags, seq);
+            self.internal_unindex_mentions(&post.mentions, seq);
+            self.internal_unindex_author_post(&post, seq);
+            self.internal_unindex_post(&post, seq);
+        }
+        if let Some(mut revisions) = self.revisions.remove(post_id) {
+            revisions.clear();
+        }
+        Some(post)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use near_sdk::test_utils::{ accounts, VMContextBuilder };
+    use near_sdk::testing_env;
     //for testing purposes
     use crate::IMAGE;
+    use near_contract_standards::storage_management::StorageManagement;
+
+    //storage deposit of the test accounts, 10 NEAR
+    pub(crate) const TEST_STORAGE_DEPOSIT: Balance = 10_000_000_000_000_000_000_000_000;
+
+    //sets the account calling the contract
+    pub(crate) fn set_caller(account_id: AccountId) {
+        testing_env!(VMContextBuilder::new().predecessor_account_id(account_id).build());
+    }
+
+    //registers every account the tests write with for storage, keeping the current context
+    pub(crate) fn register_accounts(post: &mut Posts) {
+        let context = VMContextBuilder::new()
+            .predecessor_account_id(env::predecessor_account_id())
+            .attached_deposit(env::attached_deposit())
+            .block_timestamp(env::block_timestamp())
+            .block_index(env::block_height())
+            .build();
+        let named: Vec<AccountId> = ["alice.near", "bob.near"].iter().map(|id| id.parse().unwrap()).collect();
+        for account_id in (0..6).map(accounts).chain(named) {
+            set_deposit(accounts(0), TEST_STORAGE_DEPOSIT);
+            post.storage_deposit(Some(account_id), None);
+        }
+        testing_env!(context);
+    }
+
+    //new contract owned by accounts(0), with the test accounts registered for storage
+    pub(crate) fn new_contract() -> Posts {
+        let mut post = Posts::new(accounts(0));
+        register_accounts(&mut post);
+        post
+    }
+
+    //new contract with post "0" by accounts(1), which is left as the caller
+    pub(crate) fn new_contract_with_post() -> Posts {
+        set_caller(accounts(1));
+        let mut post = new_contract();
+        post.new_post("title".to_string(), "body".to_string(), None);
+        post
+    }
+
+    //ids of `posts`, in order
+    pub(crate) fn ids(posts: &[Post]) -> Vec<String> {
+        posts.iter().map(|post| post.id.clone()).collect()
+    }
+
+    //sets the account calling the contract and the deposit it attaches
+    pub(crate) fn set_deposit(account_id: AccountId, deposit: Balance) {
+        testing_env!(
+            VMContextBuilder::new().predecessor_account_id(account_id).attached_deposit(deposit).build()
+        );
+    }
 
     #[test]
     pub fn new_post_with_title() {
-        let mut post = Posts::new();
+        let mut post = new_contract();
         post.new_post("title".to_string(), "body".to_string(), Some(IMAGE.to_string()));
         post.new_post("title 1".to_string(), "body 1".to_string(), Some(IMAGE.to_string()));
-        assert_eq!(post.posts.len(), 2);
+        assert_eq!(post.post_index.len(), 2);
+        assert_eq!(post.get_posts()[0].image, Some(IMAGE.to_string()));
+    }
+
+    //test new post rejects an image that is not a CID
+    #[test]
+    #[should_panic(expected = "Invalid image CID")]
+    pub fn new_post_with_invalid_image() {
+        let mut post = new_contract();
+        post.new_post(
+            "title".to_string(),
+            "body".to_string(),
+            Some("https://static.vecteezy.com/packs/media/vectors/term-bg-1-3d6355ab.jpg".to_string())
+        );
+    }
+
+    //test post ids follow the order posts were created in
+    #[test]
+    pub fn new_post_ids() {
+        let mut post = new_contract();
+        post.new_post("title".to_string(), "body".to_string(), None);
+        post.new_post("title 1".to_string(), "body 1".to_string(), None);
+        post.delete_post("1".to_string());
+        post.new_post("title 2".to_string(), "body 2".to_string(), None);
+        assert_eq!(ids(&post.get_posts()), vec!["0", "2"]);
     }
 
     //testing to get all posts
     #[test]
     pub fn get_posts() {
-        let mut post = Posts::new();
+        let mut post = new_contract();
         post.new_post("title".to_string(), "body".to_string(), Some(IMAGE.to_string()));
         post.new_post("title 1".to_string(), "body 1".to_string(), Some(IMAGE.to_string()));
         let posts = post.get_posts();
@@ -164,13 +561,94 @@ mod tests {
         assert_eq!(posts[0].body, "body".to_string());
     }
 
+    //test the legacy view stops at MAX_PAGE_SIZE posts
+    #[test]
+    pub fn get_posts_is_capped() {
+        let mut post = new_contract();
+        for i in 0..MAX_PAGE_SIZE + 5 {
+            //fresh context so the loop doesn't run out of prepaid gas
+            set_caller(accounts(1));
+            post.new_post(format!("title {}", i), "body".to_string(), None);
+        }
+        let posts = post.get_posts();
+        assert_eq!(posts.len() as u64, MAX_PAGE_SIZE);
+        assert_eq!(posts[0].id, "0".to_string());
+    }
+
+    //test paging through posts in every order
+    #[test]
+    pub fn list_posts_sorted() {
+        let mut post = new_contract();
+        for i in 0..5 {
+            post.new_post(format!("title {}", i), "body".to_string(), None);
+        }
+        assert_eq!(ids(&post.list_posts(None, None, None, None)), vec!["4", "3", "2", "1", "0"]);
+        assert_eq!(ids(&post.list_posts(Some(U64::from(1)), Some(2), None, None)), vec!["3", "2"]);
+        assert_eq!(ids(&post.list_posts(Some(U64::from(3)), Some(5), Some(SortOrder::Oldest), None)), vec!["3", "4"]);
+        assert!(post.list_posts(Some(U64::from(5)), None, None, None).is_empty());
+
+        //post 1 got the most, posts without donations follow newest first
+        for (post_id, amount) in [("1", 300), ("3", 100), ("1", 50)] {
+            let mut stored = post.internal_get_post(&post_id.to_string()).unwrap();
+            let old_amount = stored.donation_amount.0;
+            stored.donation_amount = U128::from(old_amount + amount);
+            post.internal_save_post(&stored);
+            post.internal_rerank_post(&stored.id, old_amount, old_amount + amount);
+        }
+        assert_eq!(ids(&post.list_posts(None, None, Some(SortOrder::MostDonated), None)), vec!["1", "3", "4", "2", "0"]);
+        post.delete_post("1".to_string());
+        assert_eq!(ids(&post.list_posts(None, Some(2), Some(SortOrder::MostDonated), None)), vec!["3", "4"]);
+    }
+
+    //test posts record when they were created and can be filtered by time
+    #[test]
+    pub fn posts_by_time() {
+        let mut post = new_contract();
+        for (timestamp, block_height) in [(100, 10), (200, 20), (200, 21), (300, 30)] {
+            testing_env!(
+                VMContextBuilder::new().block_timestamp(timestamp).block_index(block_height).build()
+            );
+            post.new_post("title".to_string(), "body".to_string(), None);
+        }
+        let stored = post.get_post("1".to_string()).unwrap();
+        assert_eq!(stored.created_at, U64::from(200));
+        assert_eq!(stored.block_height, U64::from(20));
+        assert_eq!(stored.updated_at, None);
+
+        assert_eq!(ids(&post.get_posts_by_time(None, None, None, None)), vec!["3", "2", "1", "0"]);
+        assert_eq!(ids(&post.get_posts_by_time(Some(U64::from(200)), Some(U64::from(300)), None, None)), vec![
+            "2",
+            "1"
+        ]);
+        assert_eq!(ids(&post.get_posts_by_time(Some(U64::from(150)), None, Some(U64::from(1)), Some(2))), vec![
+            "2",
+            "1"
+        ]);
+        post.delete_post("2".to_string());
+        assert_eq!(ids(&post.get_posts_by_time(None, Some(U64::from(250)), None, None)), vec!["1", "0"]);
+    }
+
+    //test limits above MAX_PAGE_SIZE are capped
+    #[test]
+    pub fn list_posts_limit_is_capped() {
+        let mut post = new_contract();
+        for i in 0..MAX_PAGE_SIZE + 1 {
+            set_caller(accounts(1));
+            post.new_post(format!("title {}", i), "body".to_string(), None);
+        }
+        assert_eq!(post.list_posts(None, Some(1000), None, None).len() as u64, MAX_PAGE_SIZE);
+        assert_eq!(post.list_posts(None, None, None, None).len() as u64, DEFAULT_PAGE_SIZE);
+        assert_eq!(post.get_post("7".to_string()).unwrap().title, "title 7".to_string());
+        assert_eq!(post.get_post("500".to_string()), None);
+    }
+
     //test search post function
     #[test]
     pub fn search_posts() {
-        let mut post = Posts::new();
+        let mut post = new_contract();
         post.new_post("title".to_string(), "body".to_string(), Some(IMAGE.to_string()));
         post.new_post("title 1".to_string(), "body 1".to_string(), Some(IMAGE.to_string()));
-        let posts = post.search_posts("title".to_string());
+        let posts = post.search_posts("title".to_string(), None, None, None, None);
         assert_eq!(posts.len(), 2);
         assert_eq!(posts[1].body, "body 1".to_string());
     }
@@ -178,35 +656,84 @@ mod tests {
     //test delete posts
     #[test]
     pub fn delete_post() {
-        let mut post = Posts::new();
+        let mut post = new_contract();
         post.new_post("title".to_string(), "body".to_string(), Some(IMAGE.to_string()));
         post.new_post("title 1".to_string(), "body 1".to_string(), Some(IMAGE.to_string()));
-        post.delete_post(post.posts[0].id.to_string());
         let posts = post.get_posts();
-        assert_eq!(posts.len(), 1);
+        post.delete_post(posts[0].id.to_string());
+        let posts_after = post.get_posts();
+        assert_eq!(posts_after.len(), 1);
+        assert_eq!(posts_after[0].id, posts[1].id);
+        assert!(post.posts.get(&posts[0].id).is_none());
     }
 
-    //test success donate function
+    //test the author can delete their own post
     #[test]
-    pub fn sucess_donate_author() {
-        let mut post = Posts::new();
-        post.new_post("title".to_string(), "body".to_string(), Some(IMAGE.to_string()));
-        post.new_post("title 1".to_string(), "body 1".to_string(), Some(IMAGE.to_string()));
-        let donate1 = post.donate_author(post.posts[0].id.to_string(), U128::from(100));
-        let donate2 = post.donate_author(post.posts[0].id.to_string(), U128::from(100));
-        let donate3 = post.donate_author(post.posts[0].id.to_string(), U128::from(100));
-        assert_eq!(post.posts[0].donation_amount, U128::from(300));
+    pub fn delete_own_post() {
+        set_caller(accounts(0));
+        let mut post = new_contract();
+        set_caller(accounts(1));
+        post.new_post("title".to_string(), "body".to_string(), None);
+        post.delete_post("0".to_string());
+        assert!(post.get_posts().is_empty());
+        assert_eq!(
+            near_sdk::test_utils::get_logs().last(),
+            Some(
+                &Event::PostDeleted(vec![PostDeletedData {
+                    post_id: "0",
+                    author_id: &accounts(1),
+                    deleted_by: &accounts(1),
+                }]).to_log()
+            )
+        );
+    }
+
+    //test other accounts can't delete a post
+    #[test]
+    #[should_panic(expected = "Only the author or a moderator can delete this post")]
+    pub fn delete_post_of_other_author() {
+        set_caller(accounts(0));
+        let mut post = new_contract();
+        set_caller(accounts(1));
+        post.new_post("title".to_string(), "body".to_string(), None);
+        set_caller(accounts(2));
+        post.delete_post("0".to_string());
+    }
+
+    //test deleting a post that doesn't exist
+    #[test]
+    #[should_panic(expected = "Post not found")]
+    pub fn delete_missing_post() {
+        let mut post = new_contract();
+        post.delete_post("0".to_string());
     }
 
-    //test fail donate function
+    //test the owner and moderators can delete any post
     #[test]
-    pub fn fail_donate_author() {
-        let mut post = Posts::new();
+    pub fn moderators_delete_post() {
+        set_caller(accounts(0));
+        let mut post = new_contract();
+        post.grant_role(accounts(2), Role::Moderator);
+        set_caller(accounts(1));
         post.new_post("title".to_string(), "body".to_string(), None);
         post.new_post("title 1".to_string(), "body 1".to_string(), None);
-        let donate1 = post.donate_author(post.posts[0].id.to_string(), U128::from(100));
-        let donate2 = post.donate_author(post.posts[0].id.to_string(), U128::from(100));
-        let donate3 = post.donate_author(post.posts[0].id.to_string(), U128::from(100));
-        assert_ne!(post.posts[0].donation_amount, U128::from(400));
+        set_caller(accounts(2));
+        post.delete_post("0".to_string());
+        set_caller(accounts(0));
+        post.delete_post("1".to_string());
+        assert!(post.get_posts().is_empty());
+        post.revoke_role(accounts(2), Role::Moderator);
+        assert!(!post.is_moderator(accounts(2)));
+        assert!(post.is_moderator(accounts(0)));
+    }
+
+    //test only admins manage moderators
+    #[test]
+    #[should_panic(expected = "Only an admin can call this method")]
+    pub fn add_moderator_not_admin() {
+        set_caller(accounts(0));
+        let mut post = new_contract();
+        set_caller(accounts(1));
+        post.grant_role(accounts(1), Role::Moderator);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,160 @@
+use crate::*;
+
+//most accounts a single post can mention
+pub const MAX_MENTIONS_PER_POST: usize = 10;
+
+//function to pick the distinct accounts @mentioned in a post, in order of appearance,
+//words after '@' that aren't valid account ids are left out
+pub fn extract_mentions(title: &str, body: &str) -> Vec<AccountId> {
+    let mut mentions: Vec<AccountId> = Vec::new();
+    for text in [title, body] {
+        let mut previous: Option<char> = None;
+        let mut chars = text.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            let starts_mention = c == '@' && !previous.map(is_account_char).unwrap_or(false);
+            previous = Some(c);
+            if !starts_mention {
+                continue;
+            }
+            let mut end = i + 1;
+            while let Some((j, next)) = chars.peek() {
+                if !is_account_char(*next) {
+                    break;
+                }
+                end = j + next.len_utf8();
+                previous = Some(*next);
+                chars.next();
+            }
+            //"@bob.near." at the end of a sentence mentions bob.near
+            let name = text[i + 1..end].trim_end_matches(['.', '-', '_']);
+            if let Ok(account_id) = name.parse::<AccountId>() {
+                if !mentions.contains(&account_id) {
+                    mentions.push(account_id);
+                }
+            }
+        }
+    }
+    mentions.truncate(MAX_MENTIONS_PER_POST);
+    mentions
+}
+
+fn is_account_char(c: char) -> bool {
+    c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.' || c == '-' || c == '_'
+}
+
+#[near_bindgen]
+impl Posts {
+    //function to get the posts mentioning an account, newest first, without hidden posts
+    pub fn get_mentions(&self, account_id: AccountId, from_index: Option<U64>, limit: Option<u64>) -> Vec<Post> {
+        let (from_index, limit) = Self::page(from_index, limit);
+        match self.mentions.get(&account_id) {
+            Some(post_ids) => post_ids
+                .iter_rev()
+                .filter_map(|(_, post_id)| self.internal_get_post(&post_id))
+                .filter(|post| !post.hidden)
+                .skip(from_index)
+                .take(limit)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl Posts {
+    //adds a post to the mentions of each of `mentions`
+    pub(crate) fn internal_index_mentions(&mut self, post_id: &String, mentions: &[AccountId], seq: u64) {
+        for account_id in mentions {
+            let mut post_ids = self.mentions.get(account_id).unwrap_or_else(|| {
+                TreeMap::new(StorageKey::MentionsPerAccount {
+                    account_hash: env::sha256_array(account_id.as_bytes()),
+                })
+            });
+            post_ids.insert(&seq, post_id);
+            self.mentions.insert(account_id, &post_ids);
+        }
+    }
+
+    //drops a post from the mentions of each of `mentions`
+    pub(crate) fn internal_unindex_mentions(&mut self, mentions: &[AccountId], seq: u64) {
+        for account_id in mentions {
+            if let Some(mut post_ids) = self.mentions.get(account_id) {
+                post_ids.remove(&seq);
+                if post_ids.is_empty() {
+                    self.mentions.remove(account_id);
+                } else {
+                    self.mentions.insert(account_id, &post_ids);
+                }
+            }
+        }
+    }
+
+    //logs a mention event for each account newly mentioned by a post, for notification services
+    pub(crate) fn log_mentions(post: &Post, mentions: &[AccountId]) {
+        for account_id in mentions {
+            Event::Mention(vec![MentionData { post_id: &post.id, author_id: &post.author, account_id }]).emit();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{ ids, new_contract, set_caller };
+    use near_sdk::test_utils::{ accounts, get_logs };
+
+    fn account(account_id: &str) -> AccountId {
+        account_id.parse().unwrap()
+    }
+
+    //test mentions are validated account ids found in title and body
+    #[test]
+    pub fn extract_account_mentions() {
+        assert_eq!(extract_mentions("Hi @alice.near", "@bob.testnet, thanks @alice.near."), vec![
+            account("alice.near"),
+            account("bob.testnet")
+        ]);
+        assert_eq!(extract_mentions("mail me at me@carol.near", "@a @-bad @x..y @dave_1.near"), vec![
+            account("dave_1.near")
+        ]);
+        assert!(extract_mentions("@ALICE.near", "").is_empty());
+        let many: Vec<String> = (0..MAX_MENTIONS_PER_POST + 2).map(|i| format!("@user{}.near", i)).collect();
+        assert_eq!(extract_mentions(&many.join(" "), "").len(), MAX_MENTIONS_PER_POST);
+    }
+
+    //test the mentions of an account are paginated newest first and follow deletions
+    #[test]
+    pub fn mentions_of_account() {
+        set_caller(accounts(1));
+        let mut post = new_contract();
+        post.new_post("hello @alice.near".to_string(), "body".to_string(), None);
+        post.new_post("title".to_string(), "cc @alice.near @carol.near".to_string(), None);
+        post.new_post("no mentions".to_string(), "body".to_string(), None);
+        post.new_post("@alice.near again".to_string(), "body".to_string(), None);
+        assert_eq!(post.get_post("1".to_string()).unwrap().mentions, vec![
+            account("alice.near"),
+            account("carol.near")
+        ]);
+        assert_eq!(ids(&post.get_mentions(account("alice.near"), None, None)), vec!["3", "1", "0"]);
+        assert_eq!(ids(&post.get_mentions(account("alice.near"), Some(U64::from(1)), Some(1))), vec!["1"]);
+
+        post.delete_post("1".to_string());
+        assert_eq!(ids(&post.get_mentions(account("alice.near"), None, None)), vec!["3", "0"]);
+        assert!(post.get_mentions(account("carol.near"), None, None).is_empty());
+        assert!(post.mentions.get(&account("carol.near")).is_none());
+    }
+
+    //test an event is logged for every mention
+    #[test]
+    pub fn mention_events() {
+        set_caller(accounts(1));
+        let mut post = new_contract();
+        post.new_post("@alice.near".to_string(), "and @carol.near".to_string(), None);
+        let logs = get_logs();
+        assert_eq!(logs.len(), 3);
+        assert_eq!(
+            logs[1],
+            r#"EVENT_JSON:{"standard":"social_near","version":"1.0.0","event":"mention","data":[{"post_id":"0","author_id":"bob","account_id":"alice.near"}]}"#
+        );
+        assert!(logs[2].contains(r#""account_id":"carol.near""#));
+    }
+}
@@ -0,0 +1,239 @@
+use crate::*;
+
+//`created_at` and `block_height` of posts stored before they were recorded
+pub const UNKNOWN_CREATION: u64 = 0;
+//version of the layout of `Posts` written by this code, a new layout bumps it and
+//gets converted in `migrate`
+pub const STATE_VERSION: u32 = 1;
+//storage key of the version the state was written with, kept beside the state itself
+const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+
+//stored form of a post, a new layout gets a new variant appended here
+#[derive(BorshDeserialize, BorshSerialize)]
+pub enum VersionedPost {
+    V1(PostV1),
+    V2(Post),
+}
+
+impl From<VersionedPost> for Post {
+    fn from(post: VersionedPost) -> Self {
+        match post {
+            VersionedPost::V1(post) => post.into(),
+            VersionedPost::V2(post) => post,
+        }
+    }
+}
+
+//post as stored in the legacy Vec state and as `VersionedPost::V1`
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct PostV1 {
+    pub id: String,
+    pub author: AccountId,
+    pub title: String,
+    pub body: String,
+    pub image: Option<String>,
+    pub donation_amount: U128,
+}
+
+//contract state before the contract moved to versioned collections
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LegacyPosts {
+    pub posts: Vec<PostV1>,
+}
+
+impl From<PostV1> for Post {
+    fn from(post: PostV1) -> Self {
+        Self {
+            id: post.id,
+            author: post.author,
+            title: post.title,
+            body: post.body,
+            image: post.image,
+            donation_amount: post.donation_amount,
+            token_donations: BTreeMap::new(),
+            tags: Vec::new(),
+            mentions: Vec::new(),
+            reactions: BTreeMap::new(),
+            edited_at: None,
+            created_at: U64::from(UNKNOWN_CREATION),
+            block_height: U64::from(UNKNOWN_CREATION),
+            updated_at: None,
+            hidden: false,
+            storage_bytes: 0,
+        }
+    }
+}
+
+#[near_bindgen]
+impl Posts {
+    //function to carry the state over to newly deployed code, `upgrade` calls it right
+    //after the deploy, a change to the state's layout is converted here
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        require!(env::state_exists(), "No contract state to migrate");
+        let state = match Self::stored_state_version() {
+            Some(STATE_VERSION) => env::state_read().unwrap(),
+            Some(version) => panic!("Can't migrate from unknown state version {}", version),
+            None => panic!("The state has no version, convert it with migrate_legacy"),
+        };
+        Self::write_state_version();
+        state
+    }
+
+    //function to convert the Vec based state of the first version of the contract, which had
+    //no owner, to be called once right after deploying the new code over it
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate_legacy(owner_id: AccountId) -> Self {
+        if let Some(version) = Self::stored_state_version() {
+            panic!("The state is already at version {}, upgrade it with migrate", version);
+        }
+        let old: LegacyPosts = env::state_read().expect("No contract state to migrate");
+        let mut state = Self::new(owner_id);
+        for post in old.posts {
+            state.internal_add_post(post.into());
+        }
+        state
+    }
+}
+
+impl Posts {
+    //records that the state is written with this code's layout
+    pub(crate) fn write_state_version() {
+        env::storage_write(STATE_VERSION_KEY, &STATE_VERSION.try_to_vec().unwrap());
+    }
+
+    //version the state was written with, None for the legacy state, which had none
+    fn stored_state_version() -> Option<u32> {
+        env::storage_read(STATE_VERSION_KEY).map(|version| {
+            u32::try_from_slice(&version).expect("Invalid state version")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::accounts;
+
+    fn legacy_post(id: &str, title: &str, donation: u128) -> PostV1 {
+        PostV1 {
+            id: id.to_string(),
+            author: "alice.near".parse().unwrap(),
+            title: title.to_string(),
+            body: format!("{} body", title),
+            image: None,
+            donation_amount: U128::from(donation),
+        }
+    }
+
+    //test migrating the old Vec based state
+    #[test]
+    pub fn migrate_legacy_state() {
+        env::state_write(&LegacyPosts {
+            posts: vec![legacy_post("a", "first", 0), legacy_post("b", "second", 250)],
+        });
+        let mut state = Posts::migrate_legacy(accounts(0));
+        let posts = state.get_posts();
+        assert_eq!(posts.len(), 2);
+        assert_eq!(posts[0].id, "a".to_string());
+        assert_eq!(posts[1].title, "second".to_string());
+        assert_eq!(state.get_donations("b".to_string()), Some(250));
+        assert_eq!(state.next_seq, 2);
+        assert_eq!(Posts::stored_state_version(), Some(STATE_VERSION));
+    }
+
+    //test the migrated state keeps working with new posts
+    #[test]
+    pub fn migrated_state_accepts_new_posts() {
+        env::state_write(&LegacyPosts { posts: vec![legacy_post("a", "first", 0)] });
+        let mut state = Posts::migrate_legacy(accounts(0));
+        crate::tests::register_accounts(&mut state);
+        state.new_post("title".to_string(), "body".to_string(), None);
+        crate::tests::set_caller("alice.near".parse().unwrap());
+        state.delete_post("a".to_string());
+        let posts = state.get_posts();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].id, "1".to_string());
+        assert_eq!(posts[0].title, "title".to_string());
+    }
+
+    //test migrating without any state
+    #[test]
+    #[should_panic(expected = "No contract state to migrate")]
+    pub fn migrate_without_state() {
+        Posts::migrate_legacy(accounts(0));
+    }
+
+    //test the current state isn't read as the legacy one
+    #[test]
+    #[should_panic(expected = "The state is already at version 1, upgrade it with migrate")]
+    pub fn migrate_legacy_current_state() {
+        let post = crate::tests::new_contract();
+        env::state_write(&post);
+        Posts::migrate_legacy(accounts(0));
+    }
+
+    //test the migration run by `upgrade` keeps the state written by this version
+    #[test]
+    pub fn migrate_current_state() {
+        let mut post = crate::tests::new_contract_with_post();
+        post.edit_post("0".to_string(), "title".to_string(), "body #news".to_string(), None);
+        env::state_write(&post);
+
+        let mut state = Posts::migrate();
+        assert_eq!(state.get_owner(), accounts(0));
+        assert_eq!(state.get_posts_by_tag("news".to_string(), None, None)[0].id, "0".to_string());
+        assert_eq!(state.get_post_revisions("0".to_string(), None, None).len(), 1);
+        state.new_post("title 1".to_string(), "body 1".to_string(), None);
+        assert_eq!(crate::tests::ids(&state.get_posts()), vec!["0", "1"]);
+    }
+
+    //test the state version is checked before the state is read
+    #[test]
+    #[should_panic(expected = "Can't migrate from unknown state version 7")]
+    pub fn migrate_unknown_version() {
+        let post = crate::tests::new_contract();
+        env::state_write(&post);
+        env::storage_write(STATE_VERSION_KEY, &7u32.try_to_vec().unwrap());
+        Posts::migrate();
+    }
+
+    //test the legacy state isn't read as the current one
+    #[test]
+    #[should_panic(expected = "The state has no version, convert it with migrate_legacy")]
+    pub fn migrate_legacy_state_without_version() {
+        env::state_write(&LegacyPosts { posts: vec![legacy_post("a", "first", 0)] });
+        Posts::migrate();
+    }
+
+    //test upgrading without any state
+    #[test]
+    #[should_panic(expected = "No contract state to migrate")]
+    pub fn migrate_current_without_state() {
+        Posts::migrate();
+    }
+
+    //test stored posts are read back through their version
+    #[test]
+    pub fn versioned_post_roundtrip() {
+        let post: Post = legacy_post("a", "first", 10).into();
+        let bytes = VersionedPost::V2(post.clone()).try_to_vec().unwrap();
+        assert_eq!(bytes[0], 1);
+        let stored = VersionedPost::try_from_slice(&bytes).unwrap();
+        assert_eq!(Post::from(stored), post);
+    }
+
+    //test posts stored with the first layout are upgraded on read
+    #[test]
+    pub fn upgrade_v1_post() {
+        let mut state = Posts::new(accounts(0));
+        let old = legacy_post("a", "first", 10);
+        state.posts.insert(&"a".to_string(), &VersionedPost::V1(old.clone()));
+        let post = state.internal_get_post(&"a".to_string()).unwrap();
+        assert_eq!(post.title, old.title);
+        assert_eq!(post.donation_amount, U128::from(10));
+        assert!(post.token_donations.is_empty());
+    }
+}
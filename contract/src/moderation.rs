@@ -0,0 +1,426 @@
+use crate::*;
+
+//longest note accepted with a report or a moderation action
+pub const MAX_MODERATION_NOTE_LEN: usize = 500;
+//most open reports a post can have, so that acting on it closes them all within a single call
+pub const MAX_REPORTS_PER_POST: u64 = 50;
+//moderation actions kept in the audit log, so its storage stays bounded
+pub const MAX_AUDIT_ENTRIES: u64 = 1_000;
+
+//why a post was reported
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum ReportReason {
+    Spam,
+    Harassment,
+    HateSpeech,
+    Violence,
+    Nudity,
+    Misinformation,
+    Other,
+}
+
+//a report waiting in the moderation queue
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Report {
+    pub id: String,
+    pub post_id: String,
+    pub reporter: AccountId,
+    pub reason: ReportReason,
+    pub note: Option<String>,
+    //block timestamp in nanoseconds of when the post was reported
+    pub timestamp: U64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum ModerationAction {
+    Hide,
+    Restore,
+    Remove,
+    //the reports were looked at and nothing was done to the post
+    Dismiss,
+}
+
+//an entry of the moderation audit log
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuditEntry {
+    pub post_id: String,
+    pub action: ModerationAction,
+    pub moderator: AccountId,
+    pub note: Option<String>,
+    //number of open reports the action closed
+    pub reports_closed: u64,
+    //block timestamp in nanoseconds of the action
+    pub timestamp: U64,
+}
+
+#[near_bindgen]
+impl Posts {
+    //function to report a post to the moderators, once per account and post,
+    //the report's storage is charged to the reporter until a moderator acts on it
+    pub fn report_post(&mut self, post_id: String, reason: ReportReason, note: Option<String>) -> String {
+        Self::assert_valid_note(&note);
+        let post = self.internal_get_post(&post_id).expect("Post not found");
+        let reporter = env::predecessor_account_id();
+        require!(reporter != post.author, "Can't report your own post");
+        let initial_usage = env::storage_usage();
+        let mut reporters = self.post_reports.get(&post_id).unwrap_or_else(|| {
+            UnorderedMap::new(StorageKey::PostReporters { post_hash: env::sha256_array(post_id.as_bytes()) })
+        });
+        require!(reporters.get(&reporter).is_none(), "You already reported this post");
+        require!(reporters.len() < MAX_REPORTS_PER_POST, "The post has too many open reports already");
+        let seq = self.next_report_seq;
+        self.next_report_seq += 1;
+        reporters.insert(&reporter, &seq);
+        let report = Report {
+            id: seq.to_string(),
+            post_id,
+            reporter,
+            reason,
+            note,
+            timestamp: U64::from(env::block_timestamp()),
+        };
+        self.reports.insert(&seq, &report);
+        //the set of reporters is shared by everyone reporting the post, so it isn't charged
+        self.internal_charge_storage(&report.reporter, initial_usage);
+        self.post_reports.insert(&report.post_id, &reporters);
+        report.id
+    }
+
+    //function to hide a post from `list_posts` and `search_posts`, moderators only
+    pub fn hide_post(&mut self, post_id: String, note: Option<String>) {
+        self.internal_set_post_hidden(post_id, true, note);
+    }
+
+    //function to show a hidden post again, moderators only
+    pub fn restore_post(&mut self, post_id: String, note: Option<String>) {
+        self.internal_set_post_hidden(post_id, false, note);
+    }
+
+    //function to delete a post for good, moderators only
+    pub fn remove_post(&mut self, post_id: String, note: Option<String>) {
+        self.assert_role(Role::Moderator);
+        Self::assert_valid_note(&note);
+        let post = self.internal_get_post(&post_id).expect("Post not found");
+        self.internal_delete_post(post, &env::predecessor_account_id(), note);
+    }
+
+    //function to close the reports on a post without acting on it, moderators only
+    pub fn dismiss_reports(&mut self, post_id: String, note: Option<String>) {
+        self.assert_role(Role::Moderator);
+        Self::assert_valid_note(&note);
+        require!(self.post_reports.get(&post_id).is_some(), "The post has no open reports");
+        self.internal_log_moderation(&post_id, ModerationAction::Dismiss, note);
+    }
+
+    //function to get a page of the open reports, oldest first
+    pub fn get_reports(&self, from_index: Option<U64>, limit: Option<u64>) -> Vec<Report> {
+        let (from_index, limit) = Self::page(from_index, limit);
+        self.reports.iter().skip(from_index).take(limit).map(|(_, report)| report).collect()
+    }
+
+    //function to get a page of the open reports on a post
+    pub fn get_post_reports(&self, post_id: String, from_index: Option<U64>, limit: Option<u64>) -> Vec<Report> {
+        let (from_index, limit) = Self::page(from_index, limit);
+        match self.post_reports.get(&post_id) {
+            Some(reporters) => reporters
+                .values()
+                .skip(from_index)
+                .take(limit)
+                .filter_map(|seq| self.reports.get(&seq))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    //function to get a page of the moderation audit log, oldest first
+    pub fn get_audit_log(&self, from_index: Option<U64>, limit: Option<u64>) -> Vec<AuditEntry> {
+        let (from_index, limit) = Self::page(from_index, limit);
+        let len = self.audit_log.len();
+        //once full, the oldest entry is the one the next action overwrites
+        let oldest = if len < MAX_AUDIT_ENTRIES { 0 } else { self.next_audit_seq % MAX_AUDIT_ENTRIES };
+        (from_index as u64..len)
+            .take(limit)
+            .filter_map(|index| self.audit_log.get((oldest + index) % len))
+            .collect()
+    }
+}
+
+impl Posts {
+    fn assert_valid_note(note: &Option<String>) {
+        if let Some(note) = note {
+            require!(note.len() <= MAX_MODERATION_NOTE_LEN, "Note is too long");
+        }
+    }
+
+    fn internal_set_post_hidden(&mut self, post_id: String, hidden: bool, note: Option<String>) {
+        self.assert_role(Role::Moderator);
+        Self::assert_valid_note(&note);
+        let mut post = self.internal_get_post(&post_id).expect("Post not found");
+        require!(post.hidden != hidden, if hidden { "Post is already hidden" } else { "Post is not hidden" });
+        post.hidden = hidden;
+        self.internal_update_post(post);
+        let action = if hidden { ModerationAction::Hide } else { ModerationAction::Restore };
+        self.internal_log_moderation(&post_id, action, note);
+    }
+
+    //closes the open reports on a post, crediting each reporter, returns how many were closed
+    pub(crate) fn internal_close_reports(&mut self, post_id: &String) -> u64 {
+        let mut reporters = match self.post_reports.get(post_id) {
+            Some(reporters) => reporters,
+            None => return 0,
+        };
+        let reports = reporters.to_vec();
+        //newest first, undoing the reports in the order they were charged
+        for (reporter, seq) in reports.iter().rev() {
+            let initial_usage = env::storage_usage();
+            self.reports.remove(seq);
+            reporters.remove(reporter);
+            self.internal_charge_storage(reporter, initial_usage);
+        }
+        self.post_reports.remove(post_id);
+        reports.len() as u64
+    }
+
+    //closes the reports on a post and records what a moderator did to it
+    pub(crate) fn internal_log_moderation(&mut self, post_id: &String, action: ModerationAction, note: Option<String>) {
+        let moderator = env::predecessor_account_id();
+        let reports_closed = self.internal_close_reports(post_id);
+        let entry = AuditEntry {
+            post_id: post_id.clone(),
+            action,
+            moderator: moderator.clone(),
+            note,
+            reports_closed,
+            timestamp: U64::from(env::block_timestamp()),
+        };
+        if self.audit_log.len() < MAX_AUDIT_ENTRIES {
+            self.audit_log.push(&entry);
+        } else {
+            self.audit_log.replace(self.next_audit_seq % MAX_AUDIT_ENTRIES, &entry);
+        }
+        self.next_audit_seq += 1;
+        Event::PostModerated(vec![PostModeratedData { post_id, action, moderator_id: &moderator }]).emit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{ ids, new_contract, new_contract_with_post, set_caller, set_deposit, TEST_STORAGE_DEPOSIT };
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::test_utils::{ accounts, get_logs };
+
+    //contract owned by accounts(0) with accounts(3) as moderator and a post by accounts(1)
+    //reported by accounts(2)
+    fn setup() -> Posts {
+        let mut post = new_contract_with_post();
+        set_caller(accounts(0));
+        post.grant_role(accounts(3), Role::Moderator);
+        set_caller(accounts(2));
+        post.report_post("0".to_string(), ReportReason::Spam, Some("buy my token".to_string()));
+        post
+    }
+
+    //test reports are queued oldest first
+    #[test]
+    pub fn report_queue() {
+        let mut post = setup();
+        set_caller(accounts(1));
+        post.new_post("other".to_string(), "body".to_string(), None);
+        set_caller(accounts(4));
+        post.report_post("1".to_string(), ReportReason::HateSpeech, None);
+        post.report_post("0".to_string(), ReportReason::Other, None);
+
+        let reports = post.get_reports(None, None);
+        assert_eq!(reports.iter().map(|report| report.post_id.as_str()).collect::<Vec<_>>(), vec!["0", "1", "0"]);
+        assert_eq!(reports[0].reporter, accounts(2));
+        assert_eq!(reports[0].reason, ReportReason::Spam);
+        assert_eq!(reports[0].note, Some("buy my token".to_string()));
+        assert_eq!(post.get_reports(Some(U64::from(1)), Some(1))[0].id, "1".to_string());
+        assert_eq!(post.get_post_reports("0".to_string(), None, None).len(), 2);
+
+        //the reason is sent as snake case
+        let json = near_sdk::serde_json::to_value(&reports[1]).unwrap();
+        assert_eq!(json["reason"], "hate_speech");
+    }
+
+    //test reporting the same post twice
+    #[test]
+    #[should_panic(expected = "You already reported this post")]
+    pub fn report_twice() {
+        let mut post = setup();
+        post.report_post("0".to_string(), ReportReason::Violence, None);
+    }
+
+    //test reports per post are capped
+    #[test]
+    #[should_panic(expected = "The post has too many open reports already")]
+    pub fn report_too_many() {
+        let mut post = setup();
+        for i in 1..=MAX_REPORTS_PER_POST {
+            let reporter: AccountId = format!("reporter{}.near", i).parse().unwrap();
+            set_deposit(reporter.clone(), TEST_STORAGE_DEPOSIT);
+            post.storage_deposit(None, None);
+            post.report_post("0".to_string(), ReportReason::Spam, None);
+        }
+    }
+
+    //test reporting your own post
+    #[test]
+    #[should_panic(expected = "Can't report your own post")]
+    pub fn report_own_post() {
+        let mut post = setup();
+        set_caller(accounts(1));
+        post.report_post("0".to_string(), ReportReason::Spam, None);
+    }
+
+    //test hidden posts are left out of the listings but stay fetchable by id
+    #[test]
+    pub fn hide_and_restore() {
+        let mut post = setup();
+        set_caller(accounts(4));
+        post.follow(accounts(1));
+        set_caller(accounts(1));
+        post.new_post("title 1".to_string(), "body #news @eugene".to_string(), None);
+        post.new_post("title 2".to_string(), "body #news @eugene".to_string(), None);
+        set_caller(accounts(3));
+        post.hide_post("2".to_string(), Some("spam".to_string()));
+        post.hide_post("0".to_string(), Some("spam".to_string()));
+
+        assert!(post.get_post("0".to_string()).unwrap().hidden);
+        assert_eq!(ids(&post.get_posts()), vec!["1"]);
+        assert_eq!(ids(&post.list_posts(None, None, None, None)), vec!["1"]);
+        assert_eq!(ids(&post.search_posts("title".to_string(), None, None, None, None)), vec!["1"]);
+        assert!(post.get_reports(None, None).is_empty());
+
+        //the other views skip them before paging too
+        assert_eq!(ids(&post.get_posts_by_tag("news".to_string(), None, Some(1))), vec!["1"]);
+        assert_eq!(ids(&post.get_mentions(accounts(4), None, Some(1))), vec!["1"]);
+        assert_eq!(ids(&post.get_posts_by_time(None, None, None, Some(1))), vec!["1"]);
+        assert_eq!(ids(&post.get_feed(accounts(4), None, None).posts), vec!["1"]);
+        assert_eq!(post.get_trending_tags(None, None)[0].count, 1);
+
+        //moderators can still list them
+        assert_eq!(ids(&post.list_posts(None, None, None, Some(true))), vec!["2", "1", "0"]);
+        assert_eq!(ids(&post.search_posts("title".to_string(), None, None, None, Some(true))), vec!["0", "1", "2"]);
+
+        post.restore_post("0".to_string(), None);
+        post.restore_post("2".to_string(), None);
+        assert!(!post.get_post("0".to_string()).unwrap().hidden);
+        assert_eq!(ids(&post.get_posts()), vec!["0", "1", "2"]);
+    }
+
+    //test every action goes to the audit log and closes the reports
+    #[test]
+    pub fn audit_log() {
+        let mut post = setup();
+        set_caller(accounts(3));
+        post.hide_post("0".to_string(), Some("spam".to_string()));
+        post.restore_post("0".to_string(), None);
+        set_caller(accounts(4));
+        post.report_post("0".to_string(), ReportReason::Misinformation, None);
+        set_caller(accounts(3));
+        post.dismiss_reports("0".to_string(), None);
+        post.remove_post("0".to_string(), Some("repeat offender".to_string()));
+        assert!(post.get_post("0".to_string()).is_none());
+
+        let log = post.get_audit_log(None, None);
+        let actions: Vec<ModerationAction> = log.iter().map(|entry| entry.action).collect();
+        assert_eq!(actions, vec![
+            ModerationAction::Hide,
+            ModerationAction::Restore,
+            ModerationAction::Dismiss,
+            ModerationAction::Remove,
+        ]);
+        assert_eq!(log[0].moderator, accounts(3));
+        assert_eq!(log[0].note, Some("spam".to_string()));
+        assert_eq!(log[0].reports_closed, 1);
+        assert_eq!(log[2].reports_closed, 1);
+        assert_eq!(log[3].note, Some("repeat offender".to_string()));
+        assert!(get_logs().contains(&r#"EVENT_JSON:{"standard":"social_near","version":"1.0.0","event":"post_moderated","data":[{"post_id":"0","action":"remove","moderator_id":"danny"}]}"#.to_string()));
+    }
+
+    //test the audit log keeps the latest actions once full
+    #[test]
+    pub fn audit_log_is_capped() {
+        let mut post = setup();
+        for i in 0..MAX_AUDIT_ENTRIES + 2 {
+            //a fresh context per call keeps the events under the log limit
+            set_caller(accounts(3));
+            if i % 2 == 0 {
+                post.hide_post("0".to_string(), Some(i.to_string()));
+            } else {
+                post.restore_post("0".to_string(), Some(i.to_string()));
+            }
+        }
+        assert_eq!(post.audit_log.len(), MAX_AUDIT_ENTRIES);
+        let first = post.get_audit_log(None, Some(2));
+        assert_eq!(first[0].note, Some("2".to_string()));
+        assert_eq!(first[1].note, Some("3".to_string()));
+        let last = post.get_audit_log(Some(U64::from(MAX_AUDIT_ENTRIES - 1)), None);
+        assert_eq!(last.len(), 1);
+        assert_eq!(last[0].note, Some((MAX_AUDIT_ENTRIES + 1).to_string()));
+    }
+
+    //test moderators deleting through delete_post are logged too
+    #[test]
+    pub fn delete_post_is_logged() {
+        let mut post = setup();
+        set_caller(accounts(3));
+        post.delete_post("0".to_string());
+        assert_eq!(post.get_audit_log(None, None)[0].action, ModerationAction::Remove);
+        assert!(post.get_reports(None, None).is_empty());
+    }
+
+    //test closing a report gives its storage back to the reporter
+    #[test]
+    pub fn closed_reports_free_storage() {
+        set_caller(accounts(0));
+        let mut post = new_contract();
+        set_caller(accounts(1));
+        post.new_post("title".to_string(), "body".to_string(), None);
+        let before = post.storage_balance_of(accounts(2)).unwrap().available;
+        //every reporter gets its whole charge back, whoever reported first
+        for reporter in [accounts(2), accounts(4)] {
+            set_caller(reporter.clone());
+            post.report_post("0".to_string(), ReportReason::Spam, None);
+            assert!(post.storage_balance_of(reporter).unwrap().available.0 < before.0);
+        }
+        set_caller(accounts(0));
+        post.dismiss_reports("0".to_string(), None);
+        assert_eq!(post.storage_balance_of(accounts(2)).unwrap().available, before);
+        assert_eq!(post.storage_balance_of(accounts(4)).unwrap().available, before);
+    }
+
+    //test only moderators hide posts
+    #[test]
+    #[should_panic(expected = "Only a moderator can call this method")]
+    pub fn hide_not_moderator() {
+        let mut post = setup();
+        post.hide_post("0".to_string(), None);
+    }
+
+    //test hiding a hidden post
+    #[test]
+    #[should_panic(expected = "Post is already hidden")]
+    pub fn hide_twice() {
+        let mut post = setup();
+        set_caller(accounts(3));
+        post.hide_post("0".to_string(), None);
+        post.hide_post("0".to_string(), None);
+    }
+
+    //test dismissing without reports
+    #[test]
+    #[should_panic(expected = "The post has no open reports")]
+    pub fn dismiss_without_reports() {
+        let mut post = setup();
+        set_caller(accounts(3));
+        post.dismiss_reports("0".to_string(), None);
+        post.dismiss_reports("0".to_string(), None);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::*;
+
+//limits on the profile fields, in bytes
+pub const MAX_DISPLAY_NAME_LEN: usize = 64;
+pub const MAX_BIO_LEN: usize = 500;
+pub const MAX_LINK_LEN: usize = 256;
+pub const MAX_LINKS: usize = 5;
+
+//public profile of an account
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Profile {
+    pub display_name: String,
+    pub bio: String,
+    //IPFS CID of the avatar image
+    pub avatar: Option<String>,
+    pub links: Vec<String>,
+}
+
+//a post with the profile of its author, when the author has one
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PostWithAuthor {
+    #[serde(flatten)]
+    pub post: Post,
+    pub author_profile: Option<Profile>,
+}
+
+#[near_bindgen]
+impl Posts {
+    //function to create or replace the caller's profile, its storage is charged to the caller's deposit
+    pub fn set_profile(&mut self, display_name: String, bio: String, avatar: Option<String>, links: Vec<String>) {
+        require!(display_name.len() <= MAX_DISPLAY_NAME_LEN, "Display name is too long");
+        require!(bio.len() <= MAX_BIO_LEN, "Bio is too long");
+        require!(links.len() <= MAX_LINKS, "Too many links");
+        for link in &links {
+            require!(link.len() <= MAX_LINK_LEN, "Link is too long");
+            require!(
+                link.starts_with("https://") || link.starts_with("http://"),
+                "Links must be http or https URLs"
+            );
+        }
+        if let Some(cid) = &avatar {
+            if let Err(reason) = cid::validate_cid(cid) {
+                panic!("Invalid avatar CID '{}': {}", cid, reason);
+            }
+        }
+        let account_id = env::predecessor_account_id();
+        let initial_usage = env::storage_usage();
+        self.profiles.insert(&account_id, &Profile { display_name, bio, avatar, links });
+        self.internal_charge_storage(&account_id, initial_usage);
+    }
+
+    //function to delete the caller's profile and free its storage
+    pub fn delete_profile(&mut self) {
+        let account_id = env::predecessor_account_id();
+        let initial_usage = env::storage_usage();
+        self.profiles.remove(&account_id);
+        self.internal_charge_storage(&account_id, initial_usage);
+    }
+
+    pub fn get_profile(&self, account_id: AccountId) -> Option<Profile> {
+        self.profiles.get(&account_id)
+    }
+
+    //function to get a single post with its author's profile
+    pub fn get_post_with_author(&self, post_id: String) -> Option<PostWithAuthor> {
+        self.internal_get_post(&post_id).map(|post| self.internal_with_author(post))
+    }
+
+    //function to get a page of posts like `list_posts`, each with its author's profile
+    pub fn list_posts_with_authors(
+        &self,
+        from_index: Option<U64>,
+        limit: Option<u64>,
+        sort: Option<SortOrder>
+    ) -> Vec<PostWithAuthor> {
+        self.list_posts(from_index, limit, sort, None)
+            .into_iter()
+            .map(|post| self.internal_with_author(post))
+            .collect()
+    }
+}
+
+impl Posts {
+    fn internal_with_author(&self, post: Post) -> PostWithAuthor {
+        let author_profile = self.profiles.get(&post.author);
+        PostWithAuthor { post, author_profile }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{ new_contract, set_caller };
+    use near_sdk::test_utils::accounts;
+    //for testing purposes
+    use crate::IMAGE;
+
+    fn set_profile(post: &mut Posts, display_name: &str) {
+        post.set_profile(
+            display_name.to_string(),
+            "bio".to_string(),
+            Some(IMAGE.to_string()),
+            vec!["https://example.com".to_string()]
+        );
+    }
+
+    //test setting and replacing a profile
+    #[test]
+    pub fn set_and_get_profile() {
+        set_caller(accounts(1));
+        let mut post = new_contract();
+        assert!(post.get_profile(accounts(1)).is_none());
+        set_profile(&mut post, "Bob");
+        set_profile(&mut post, "Bobby");
+        let profile = post.get_profile(accounts(1)).unwrap();
+        assert_eq!(profile.display_name, "Bobby".to_string());
+        assert_eq!(profile.avatar, Some(IMAGE.to_string()));
+        assert_eq!(profile.links, vec!["https://example.com".to_string()]);
+        assert!(post.get_profile(accounts(2)).is_none());
+    }
+
+    //test posts come back with their author's profile
+    #[test]
+    pub fn posts_with_authors() {
+        set_caller(accounts(1));
+        let mut post = new_contract();
+        set_profile(&mut post, "Bob");
+        post.new_post("title".to_string(), "body".to_string(), None);
+        set_caller(accounts(2));
+        post.new_post("title 1".to_string(), "body 1".to_string(), None);
+
+        let with_author = post.get_post_with_author("0".to_string()).unwrap();
+        assert_eq!(with_author.author_profile.unwrap().display_name, "Bob".to_string());
+        let posts = post.list_posts_with_authors(None, None, None);
+        assert_eq!(posts[0].post.id, "1".to_string());
+        assert!(posts[0].author_profile.is_none());
+        assert!(posts[1].author_profile.is_some());
+
+        //the post fields stay at the top level of the JSON
+        let json = near_sdk::serde_json::to_value(&posts[1]).unwrap();
+        assert_eq!(json["title"], "title");
+        assert_eq!(json["author_profile"]["display_name"], "Bob");
+    }
+
+    //test overlong display names
+    #[test]
+    #[should_panic(expected = "Display name is too long")]
+    pub fn long_display_name() {
+        let mut post = new_contract();
+        set_profile(&mut post, &"x".repeat(MAX_DISPLAY_NAME_LEN + 1));
+    }
+
+    //test links that aren't URLs
+    #[test]
+    #[should_panic(expected = "Links must be http or https URLs")]
+    pub fn invalid_link() {
+        let mut post = new_contract();
+        post.set_profile("Bob".to_string(), "bio".to_string(), None, vec!["javascript:alert(1)".to_string()]);
+    }
+
+    //test avatars that aren't CIDs
+    #[test]
+    #[should_panic(expected = "Invalid avatar CID")]
+    pub fn invalid_avatar() {
+        let mut post = new_contract();
+        post.set_profile("Bob".to_string(), "bio".to_string(), Some("avatar.png".to_string()), Vec::new());
+    }
+}
@@ -0,0 +1,245 @@
+use crate::*;
+
+//reactions an account can leave on a post, at most one of each kind
+#[derive(
+    BorshDeserialize,
+    BorshSerialize,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug
+)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum ReactionKind {
+    Like,
+    Love,
+    Laugh,
+    Wow,
+    Sad,
+    Angry,
+}
+
+impl ReactionKind {
+    pub const ALL: [ReactionKind; 6] = [
+        ReactionKind::Like,
+        ReactionKind::Love,
+        ReactionKind::Laugh,
+        ReactionKind::Wow,
+        ReactionKind::Sad,
+        ReactionKind::Angry,
+    ];
+}
+
+#[near_bindgen]
+impl Posts {
+    //function to react to a post, reacting again with the same kind takes it back,
+    //returns whether the caller now has that reaction on the post. The caller is charged
+    //for all its reaction adds, recorded beside it and refunded as is when taken back
+    pub fn react(&mut self, post_id: String, kind: ReactionKind) -> bool {
+        let mut post = self.internal_get_post(&post_id).expect("Post not found");
+        let account_id = env::predecessor_account_id();
+        self.assert_not_blocked(&post, &account_id);
+        let initial_usage = env::storage_usage();
+        let key = (post_id, kind);
+        let mut reactors = self.reactions.get(&key).unwrap_or_else(|| {
+            let key_hash = env::sha256_array(format!("{}:{:?}", key.0, kind).as_bytes());
+            UnorderedMap::new(StorageKey::Reactors { key_hash })
+        });
+        let count = post.reactions.get(&kind).copied().unwrap_or(0);
+        let reacted = match reactors.remove(&account_id) {
+            Some(bytes) => {
+                self.internal_refund_bytes(&account_id, bytes);
+                false
+            }
+            None => {
+                reactors.insert(&account_id, &0);
+                true
+            }
+        };
+        if reacted {
+            post.reactions.insert(kind, count + 1);
+        } else if count > 1 {
+            post.reactions.insert(kind, count - 1);
+        } else {
+            post.reactions.remove(&kind);
+        }
+        if reactors.is_empty() {
+            self.reactions.remove(&key);
+        } else {
+            self.reactions.insert(&key, &reactors);
+        }
+        //a reaction isn't a change to the post, so `updated_at` stays
+        self.internal_save_post(&post);
+        if reacted {
+            let bytes = env::storage_usage().saturating_sub(initial_usage);
+            self.internal_charge_bytes(&account_id, bytes);
+            reactors.insert(&account_id, &bytes);
+        }
+        Event::Reaction(vec![ReactionData { post_id: &post.id, account_id: &account_id, kind, reacted }]).emit();
+        reacted
+    }
+
+    //function to like a post, or take the like back
+    pub fn like_post(&mut self, post_id: String) -> bool {
+        self.react(post_id, ReactionKind::Like)
+    }
+
+    //function to drop up to `limit` reactions left behind by a deleted post, crediting each
+    //reactor for its entry, returns how many are still left. Anyone can call it
+    pub fn clear_reactions(&mut self, post_id: String, limit: Option<u64>) -> u64 {
+        require!(!self.posts.contains_key(&post_id), "Only reactions to deleted posts can be cleared");
+        let (_, mut limit) = Self::page(None, limit);
+        let mut left = 0;
+        for kind in ReactionKind::ALL {
+            let key = (post_id.clone(), kind);
+            let mut reactors = match self.reactions.get(&key) {
+                Some(reactors) => reactors,
+                None => continue,
+            };
+            let removed: Vec<(AccountId, StorageUsage)> = reactors.iter().take(limit).collect();
+            for (account_id, bytes) in removed.iter() {
+                reactors.remove(account_id);
+                self.internal_refund_bytes(account_id, *bytes);
+            }
+            limit -= removed.len();
+            if reactors.is_empty() {
+                self.reactions.remove(&key);
+            } else {
+                left += reactors.len();
+                self.reactions.insert(&key, &reactors);
+            }
+        }
+        left
+    }
+
+    //function to list the accounts that reacted to a post with a kind of reaction
+    pub fn get_reactors(
+        &self,
+        post_id: String,
+        kind: ReactionKind,
+        from_index: Option<U64>,
+        limit: Option<u64>
+    ) -> Vec<AccountId> {
+        let (from_index, limit) = Self::page(from_index, limit);
+        if !self.posts.contains_key(&post_id) {
+            return Vec::new();
+        }
+        match self.reactions.get(&(post_id, kind)) {
+            Some(reactors) => reactors.keys().skip(from_index).take(limit).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn has_reacted(&self, post_id: String, kind: ReactionKind, account_id: AccountId) -> bool {
+        if !self.posts.contains_key(&post_id) {
+            return false;
+        }
+        self.reactions
+            .get(&(post_id, kind))
+            .map(|reactors| reactors.get(&account_id).is_some())
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{ new_contract_with_post, set_caller };
+    use near_sdk::test_utils::accounts;
+
+    fn react(post: &mut Posts, account_id: AccountId, kind: ReactionKind) -> bool {
+        set_caller(account_id);
+        post.react("0".to_string(), kind)
+    }
+
+    //test reactions are counted per kind on the post
+    #[test]
+    pub fn react_to_post() {
+        let mut post = new_contract_with_post();
+        assert!(react(&mut post, accounts(2), ReactionKind::Like));
+        assert!(react(&mut post, accounts(3), ReactionKind::Like));
+        assert!(react(&mut post, accounts(2), ReactionKind::Laugh));
+        let stored = post.get_post("0".to_string()).unwrap();
+        assert_eq!(stored.reactions, BTreeMap::from([(ReactionKind::Like, 2), (ReactionKind::Laugh, 1)]));
+        assert_eq!(post.get_reactors("0".to_string(), ReactionKind::Like, None, None), vec![accounts(2), accounts(3)]);
+        assert_eq!(post.get_reactors("0".to_string(), ReactionKind::Like, Some(U64::from(1)), None), vec![accounts(3)]);
+        assert!(post.has_reacted("0".to_string(), ReactionKind::Laugh, accounts(2)));
+        assert!(!post.has_reacted("0".to_string(), ReactionKind::Laugh, accounts(3)));
+    }
+
+    //test reacting twice takes the reaction back
+    #[test]
+    pub fn toggle_reaction() {
+        let mut post = new_contract_with_post();
+        set_caller(accounts(2));
+        assert!(post.like_post("0".to_string()));
+        assert!(!post.like_post("0".to_string()));
+        assert!(post.get_post("0".to_string()).unwrap().reactions.is_empty());
+        assert!(post.get_reactors("0".to_string(), ReactionKind::Like, None, None).is_empty());
+        assert!(post.reactions.get(&("0".to_string(), ReactionKind::Like)).is_none());
+        assert!(post.like_post("0".to_string()));
+        assert_eq!(post.get_post("0".to_string()).unwrap().reactions.get(&ReactionKind::Like), Some(&1));
+    }
+
+    //test reactions leave `updated_at` alone
+    #[test]
+    pub fn react_keeps_updated_at() {
+        let mut post = new_contract_with_post();
+        react(&mut post, accounts(2), ReactionKind::Like);
+        react(&mut post, accounts(3), ReactionKind::Like);
+        react(&mut post, accounts(2), ReactionKind::Like);
+        assert_eq!(post.get_post("0".to_string()).unwrap().updated_at, None);
+    }
+
+    //test reaction kinds are plain strings in JSON
+    #[test]
+    pub fn reaction_json() {
+        let mut post = new_contract_with_post();
+        react(&mut post, accounts(2), ReactionKind::Wow);
+        let json = near_sdk::serde_json::to_value(post.get_post("0".to_string()).unwrap()).unwrap();
+        assert_eq!(json["reactions"], near_sdk::serde_json::json!({ "wow": 1 }));
+    }
+
+    //test reactions to a deleted post are hidden and cleared in pages
+    #[test]
+    pub fn delete_reacted_post() {
+        let mut post = new_contract_with_post();
+        react(&mut post, accounts(2), ReactionKind::Like);
+        react(&mut post, accounts(3), ReactionKind::Like);
+        react(&mut post, accounts(3), ReactionKind::Sad);
+        set_caller(accounts(1));
+        post.delete_post("0".to_string());
+        assert!(post.get_reactors("0".to_string(), ReactionKind::Like, None, None).is_empty());
+        assert!(!post.has_reacted("0".to_string(), ReactionKind::Sad, accounts(3)));
+
+        set_caller(accounts(4));
+        assert_eq!(post.clear_reactions("0".to_string(), Some(1)), 2);
+        assert_eq!(post.clear_reactions("0".to_string(), Some(1)), 1);
+        assert!(post.reactions.get(&("0".to_string(), ReactionKind::Like)).is_none());
+        assert_eq!(post.clear_reactions("0".to_string(), None), 0);
+        assert!(post.reactions.get(&("0".to_string(), ReactionKind::Sad)).is_none());
+    }
+
+    //test reactions to posts that still exist can't be cleared
+    #[test]
+    #[should_panic(expected = "Only reactions to deleted posts can be cleared")]
+    pub fn clear_reactions_live_post() {
+        let mut post = new_contract_with_post();
+        react(&mut post, accounts(2), ReactionKind::Like);
+        post.clear_reactions("0".to_string(), None);
+    }
+
+    //test reactions to posts that don't exist
+    #[test]
+    #[should_panic(expected = "Post not found")]
+    pub fn react_missing_post() {
+        let mut post = new_contract_with_post();
+        post.react("7".to_string(), ReactionKind::Like);
+    }
+}
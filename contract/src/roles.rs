@@ -0,0 +1,260 @@
+use crate::*;
+
+//gas kept back for the upgrade call itself, the rest goes to `migrate` on the new code
+const GAS_FOR_UPGRADE: Gas = Gas(10_000_000_000_000);
+
+//roles of the accounts running the contract, each one also holds the rights of those below it:
+//the owner manages admins, upgrades and transfers the contract, admins manage moderators
+//and the configuration, moderators delete posts and hide comments
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum Role {
+    Owner,
+    Admin,
+    Moderator,
+}
+
+impl Role {
+    fn holder(&self) -> &'static str {
+        match self {
+            Role::Owner => "the owner",
+            Role::Admin => "an admin",
+            Role::Moderator => "a moderator",
+        }
+    }
+
+    //role allowed to grant and revoke this one
+    fn manager(&self) -> Role {
+        match self {
+            Role::Owner | Role::Admin => Role::Owner,
+            Role::Moderator => Role::Admin,
+        }
+    }
+}
+
+#[near_bindgen]
+impl Posts {
+    //function to give `role` to an account, admins are granted by the owner and moderators by admins
+    pub fn grant_role(&mut self, account_id: AccountId, role: Role) {
+        require!(role != Role::Owner, "Use transfer_ownership to change the owner");
+        self.assert_role(role.manager());
+        if self.internal_role_members(role).insert(&account_id) {
+            Event::RoleGranted(vec![RoleData { account_id: &account_id, role, changed_by: &env::predecessor_account_id() }]).emit();
+        }
+    }
+
+    //function to take `role` back from an account, with the same rights as `grant_role`
+    pub fn revoke_role(&mut self, account_id: AccountId, role: Role) {
+        require!(role != Role::Owner, "Use transfer_ownership to change the owner");
+        self.assert_role(role.manager());
+        if self.internal_role_members(role).remove(&account_id) {
+            Event::RoleRevoked(vec![RoleData { account_id: &account_id, role, changed_by: &env::predecessor_account_id() }]).emit();
+        }
+    }
+
+    //function to hand the contract over to another account, owner only
+    pub fn transfer_ownership(&mut self, new_owner_id: AccountId) {
+        self.assert_role(Role::Owner);
+        let old_owner_id = std::mem::replace(&mut self.owner_id, new_owner_id);
+        let changed_by = env::predecessor_account_id();
+        Event::RoleRevoked(vec![RoleData { account_id: &old_owner_id, role: Role::Owner, changed_by: &changed_by }]).emit();
+        Event::RoleGranted(vec![RoleData { account_id: &self.owner_id, role: Role::Owner, changed_by: &changed_by }]).emit();
+    }
+
+    //function to deploy new code, passed as the raw input of the call, then run its `migrate`, owner only
+    pub fn upgrade(&mut self) -> Promise {
+        self.assert_role(Role::Owner);
+        let code = env::input().expect("Expected the new contract code as input");
+        require!(!code.is_empty(), "Expected the new contract code as input");
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call(
+                "migrate".to_string(),
+                Vec::new(),
+                0,
+                env::prepaid_gas() - env::used_gas() - GAS_FOR_UPGRADE
+            )
+    }
+
+    pub fn get_owner(&self) -> AccountId {
+        self.owner_id.clone()
+    }
+
+    //function to get a page of the accounts granted `role`
+    pub fn get_role_members(&self, role: Role, from_index: Option<U64>, limit: Option<u64>) -> Vec<AccountId> {
+        let (from_index, limit) = Self::page(from_index, limit);
+        match role {
+            Role::Owner => vec![self.owner_id.clone()].into_iter().skip(from_index).take(limit).collect(),
+            Role::Admin => self.admins.iter().skip(from_index).take(limit).collect(),
+            Role::Moderator => self.moderators.iter().skip(from_index).take(limit).collect(),
+        }
+    }
+
+    //function to get the roles an account was given, highest first
+    pub fn get_roles(&self, account_id: AccountId) -> Vec<Role> {
+        let mut roles = Vec::new();
+        if account_id == self.owner_id {
+            roles.push(Role::Owner);
+        }
+        if self.admins.contains(&account_id) {
+            roles.push(Role::Admin);
+        }
+        if self.moderators.contains(&account_id) {
+            roles.push(Role::Moderator);
+        }
+        roles
+    }
+
+    //whether an account has the rights of `role`, given to it or through a higher role
+    pub fn has_role(&self, account_id: AccountId, role: Role) -> bool {
+        self.internal_has_role(&account_id, role)
+    }
+
+    pub fn is_moderator(&self, account_id: AccountId) -> bool {
+        self.internal_has_role(&account_id, Role::Moderator)
+    }
+}
+
+impl Posts {
+    pub(crate) fn internal_has_role(&self, account_id: &AccountId, role: Role) -> bool {
+        if account_id == &self.owner_id {
+            return true;
+        }
+        match role {
+            Role::Owner => false,
+            Role::Admin => self.admins.contains(account_id),
+            Role::Moderator => self.admins.contains(account_id) || self.moderators.contains(account_id),
+        }
+    }
+
+    //stops callers without the rights of `role`
+    pub(crate) fn assert_role(&self, role: Role) {
+        require!(
+            self.internal_has_role(&env::predecessor_account_id(), role),
+            &format!("Only {} can call this method", role.holder())
+        );
+    }
+
+    fn internal_role_members(&mut self, role: Role) -> &mut UnorderedSet<AccountId> {
+        match role {
+            Role::Admin => &mut self.admins,
+            Role::Moderator => &mut self.moderators,
+            Role::Owner => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{ new_contract, set_caller };
+    use near_sdk::mock::VmAction;
+    use near_sdk::test_utils::{ accounts, get_created_receipts, get_logs, VMContextBuilder };
+    use near_sdk::testing_env;
+
+    //contract owned by accounts(0), with accounts(1) as admin and accounts(2) as moderator
+    fn setup() -> Posts {
+        set_caller(accounts(0));
+        let mut post = new_contract();
+        post.grant_role(accounts(1), Role::Admin);
+        set_caller(accounts(1));
+        post.grant_role(accounts(2), Role::Moderator);
+        post
+    }
+
+    //test granting, revoking and reading roles
+    #[test]
+    pub fn grant_and_revoke_roles() {
+        let mut post = setup();
+        assert_eq!(post.get_role_members(Role::Owner, None, None), vec![accounts(0)]);
+        assert_eq!(post.get_role_members(Role::Admin, None, None), vec![accounts(1)]);
+        assert_eq!(post.get_role_members(Role::Moderator, None, None), vec![accounts(2)]);
+        assert_eq!(post.get_roles(accounts(1)), vec![Role::Admin]);
+        assert!(post.get_roles(accounts(3)).is_empty());
+
+        //higher roles hold the rights of the lower ones
+        assert!(post.has_role(accounts(0), Role::Moderator));
+        assert!(post.is_moderator(accounts(1)));
+        assert!(!post.has_role(accounts(1), Role::Owner));
+        assert!(!post.has_role(accounts(2), Role::Admin));
+
+        post.revoke_role(accounts(2), Role::Moderator);
+        assert!(!post.is_moderator(accounts(2)));
+        set_caller(accounts(0));
+        post.revoke_role(accounts(1), Role::Admin);
+        assert!(post.get_role_members(Role::Admin, None, None).is_empty());
+    }
+
+    //test role changes are logged
+    #[test]
+    pub fn role_events() {
+        setup();
+        assert_eq!(get_logs(), vec![
+            r#"EVENT_JSON:{"standard":"social_near","version":"1.0.0","event":"role_granted","data":[{"account_id":"charlie","role":"moderator","changed_by":"bob"}]}"#
+        ]);
+    }
+
+    //test admins can't grant admin
+    #[test]
+    #[should_panic(expected = "Only the owner can call this method")]
+    pub fn admin_grants_admin() {
+        let mut post = setup();
+        post.grant_role(accounts(3), Role::Admin);
+    }
+
+    //test moderators can't grant moderator
+    #[test]
+    #[should_panic(expected = "Only an admin can call this method")]
+    pub fn moderator_grants_moderator() {
+        let mut post = setup();
+        set_caller(accounts(2));
+        post.grant_role(accounts(3), Role::Moderator);
+    }
+
+    //test the owner role only changes hands through transfer_ownership
+    #[test]
+    #[should_panic(expected = "Use transfer_ownership to change the owner")]
+    pub fn grant_owner() {
+        let mut post = setup();
+        set_caller(accounts(0));
+        post.grant_role(accounts(1), Role::Owner);
+    }
+
+    //test transferring the contract
+    #[test]
+    pub fn transfer_ownership() {
+        let mut post = setup();
+        set_caller(accounts(0));
+        post.transfer_ownership(accounts(3));
+        assert_eq!(post.get_owner(), accounts(3));
+        assert!(!post.has_role(accounts(0), Role::Moderator));
+        set_caller(accounts(3));
+        post.revoke_role(accounts(1), Role::Admin);
+    }
+
+    //test upgrading deploys the code and migrates
+    #[test]
+    pub fn upgrade() {
+        let mut post = setup();
+        let mut context = VMContextBuilder::new().predecessor_account_id(accounts(0)).build();
+        context.input = vec![0, 97, 115, 109];
+        testing_env!(context);
+        post.upgrade();
+        let receipt = &get_created_receipts()[0];
+        assert_eq!(receipt.receiver_id, env::current_account_id());
+        assert_eq!(receipt.actions[0], VmAction::DeployContract { code: vec![0, 97, 115, 109] });
+        //`migrate` takes no arguments
+        assert!(matches!(
+            &receipt.actions[1],
+            VmAction::FunctionCall { function_name, args, .. } if function_name == "migrate" && args.is_empty()
+        ));
+    }
+
+    //test only the owner upgrades
+    #[test]
+    #[should_panic(expected = "Only the owner can call this method")]
+    pub fn upgrade_not_owner() {
+        let mut post = setup();
+        post.upgrade();
+    }
+}
@@ -0,0 +1,174 @@
+use crate::*;
+use std::collections::BTreeSet;
+
+//longest token kept in the index, longer words are skipped
+pub const MAX_TOKEN_LEN: usize = 32;
+//most distinct tokens indexed for a single post
+pub const MAX_TOKENS_PER_POST: usize = 100;
+
+//words too common to be worth indexing
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into", "is", "it",
+    "no", "not", "of", "on", "or", "such", "that", "the", "their", "then", "there", "these",
+    "they", "this", "to", "was", "will", "with",
+];
+
+//splits text into distinct lowercase words, without stop words, in the order they first appear
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && word.len() <= MAX_TOKEN_LEN)
+        .map(|word| word.to_lowercase())
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()) && seen.insert(word.clone()))
+        .collect()
+}
+
+//tokens a post is indexed under, from its title and body, the first ones when there are too many
+fn post_tokens(post: &Post) -> Vec<String> {
+    let mut tokens = tokenize(&format!("{} {}", post.title, post.body));
+    tokens.truncate(MAX_TOKENS_PER_POST);
+    tokens
+}
+
+#[near_bindgen]
+impl Posts {
+    //function to search for posts containing every word of `search_string`, oldest first,
+    //in their title or body, ignoring case, without the authors `account_id` muted nor
+    //hidden posts unless `include_hidden` is set
+    pub fn search_posts(
+        &self,
+        search_string: String,
+        from_index: Option<U64>,
+        limit: Option<u64>,
+        account_id: Option<AccountId>,
+        include_hidden: Option<bool>
+    ) -> Vec<Post> {
+        let (from_index, limit) = Self::page(from_index, limit);
+        let include_hidden = include_hidden.unwrap_or(false);
+        let mut matches = Vec::new();
+        for token in tokenize(&search_string) {
+            match self.search_index.get(&token) {
+                Some(post_ids) => matches.push(post_ids),
+                None => return Vec::new(),
+            }
+        }
+        //walk the rarest word and check the others against it
+        matches.sort_by_key(|post_ids| post_ids.len());
+        let (rarest, others) = match matches.split_first() {
+            Some(split) => split,
+            None => return Vec::new(),
+        };
+        rarest
+            .iter()
+            .filter(|(seq, _)| others.iter().all(|post_ids| post_ids.contains_key(seq)))
+            .filter_map(|(_, post_id)| self.internal_get_post(&post_id))
+            .filter(|post| include_hidden || !post.hidden)
+            .filter(|post| match &account_id {
+                Some(account_id) => !self.internal_is_muted(account_id, &post.author),
+                None => true,
+            })
+            .skip(from_index)
+            .take(limit)
+            .collect()
+    }
+}
+
+impl Posts {
+    //adds a post under each of its tokens, in insertion order so results page stably
+    pub(crate) fn internal_index_post(&mut self, post: &Post, seq: u64) {
+        for token in post_tokens(post) {
+            let mut post_ids = self.search_index.get(&token).unwrap_or_else(|| {
+                TreeMap::new(StorageKey::SearchToken { token_hash: env::sha256_array(token.as_bytes()) })
+            });
+            post_ids.insert(&seq, &post.id);
+            self.search_index.insert(&token, &post_ids);
+        }
+    }
+
+    //drops a post from each of its tokens, and tokens left without posts
+    pub(crate) fn internal_unindex_post(&mut self, post: &Post, seq: u64) {
+        for token in post_tokens(post) {
+            if let Some(mut post_ids) = self.search_index.get(&token) {
+                post_ids.remove(&seq);
+                if post_ids.is_empty() {
+                    self.search_index.remove(&token);
+                } else {
+                    self.search_index.insert(&token, &post_ids);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{ ids, new_contract, set_caller };
+    use near_sdk::test_utils::accounts;
+
+    //test tokenizing drops case, punctuation, stop words and repeats
+    #[test]
+    pub fn tokenize_text() {
+        assert_eq!(tokenize("The NEAR protocol, and the near   Protocol!"), vec!["near", "protocol"]);
+        assert_eq!(tokenize("#rust @alice.near"), vec!["rust", "alice", "near"]);
+        assert_eq!(tokenize("Ünïcode words"), vec!["ünïcode", "words"]);
+        assert!(tokenize("the of and").is_empty());
+        assert!(tokenize(&"x".repeat(MAX_TOKEN_LEN + 1)).is_empty());
+    }
+
+    //test searching title and body with several words
+    #[test]
+    pub fn search_title_and_body() {
+        let mut post = new_contract();
+        post.new_post("Rust on NEAR".to_string(), "Writing contracts".to_string(), None);
+        post.new_post("Gardening".to_string(), "Tomatoes need sun, rust kills them".to_string(), None);
+        post.new_post("Daily log".to_string(), "Nothing about contracts".to_string(), None);
+        assert_eq!(ids(&post.search_posts("rust".to_string(), None, None, None, None)), vec!["0", "1"]);
+        assert_eq!(ids(&post.search_posts("RUST contracts".to_string(), None, None, None, None)), vec!["0"]);
+        assert_eq!(ids(&post.search_posts("contracts".to_string(), None, None, None, None)), vec!["0", "2"]);
+        assert!(post.search_posts("rust python".to_string(), None, None, None, None).is_empty());
+        assert!(post.search_posts("the".to_string(), None, None, None, None).is_empty());
+        assert!(post.search_posts("".to_string(), None, None, None, None).is_empty());
+    }
+
+    //test search results are paginated
+    #[test]
+    pub fn search_pagination() {
+        let mut post = new_contract();
+        for i in 0..5 {
+            post.new_post(format!("news {}", i), "body".to_string(), None);
+        }
+        assert_eq!(ids(&post.search_posts("news".to_string(), Some(U64::from(1)), Some(2), None, None)), vec!["1", "2"]);
+        assert_eq!(ids(&post.search_posts("news body".to_string(), Some(U64::from(4)), None, None, None)), vec!["4"]);
+
+        //deleting a post doesn't reorder the rest
+        post.delete_post("1".to_string());
+        assert_eq!(ids(&post.search_posts("news".to_string(), None, None, None, None)), vec!["0", "2", "3", "4"]);
+        assert_eq!(ids(&post.search_posts("news".to_string(), Some(U64::from(1)), Some(2), None, None)), vec!["2", "3"]);
+    }
+
+    //test deleted posts leave the index
+    #[test]
+    pub fn search_after_delete() {
+        set_caller(accounts(1));
+        let mut post = new_contract();
+        post.new_post("unique words".to_string(), "body".to_string(), None);
+        post.new_post("shared".to_string(), "body".to_string(), None);
+        post.delete_post("0".to_string());
+        assert!(post.search_posts("unique".to_string(), None, None, None, None).is_empty());
+        assert!(post.search_index.get(&"unique".to_string()).is_none());
+        assert_eq!(ids(&post.search_posts("body".to_string(), None, None, None, None)), vec!["1"]);
+    }
+
+    //test long posts are indexed under their first words
+    #[test]
+    pub fn search_long_post() {
+        let mut post = new_contract();
+        let words: Vec<String> = (0..MAX_TOKENS_PER_POST + 20).map(|i| format!("w{}", i)).collect();
+        post.new_post("title".to_string(), words.join(" "), None);
+        assert_eq!(ids(&post.search_posts("title".to_string(), None, None, None, None)), vec!["0"]);
+        assert_eq!(ids(&post.search_posts("w98".to_string(), None, None, None, None)), vec!["0"]);
+        assert!(post.search_posts(format!("w{}", MAX_TOKENS_PER_POST - 1), None, None, None, None).is_empty());
+        assert!(post.search_posts("w119".to_string(), None, None, None, None).is_empty());
+    }
+}
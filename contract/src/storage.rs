@@ -0,0 +1,433 @@
+use crate::*;
+use near_contract_standards::storage_management::{ StorageBalance, StorageBalanceBounds, StorageManagement };
+use near_sdk::{ assert_one_yocto, StorageUsage };
+
+//longest account id, used to measure the storage of a registration
+const MAX_ACCOUNT_ID_LEN: usize = 64;
+
+//storage deposit of an account and the bytes its data takes
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StorageAccount {
+    pub deposit: Balance,
+    //includes the registration itself
+    pub bytes: StorageUsage,
+}
+
+#[near_bindgen]
+impl StorageManagement for Posts {
+    //function to register `account_id`, the caller by default, or to add to its deposit,
+    //with `registration_only` anything above the minimum balance is refunded
+    #[payable]
+    fn storage_deposit(&mut self, account_id: Option<AccountId>, registration_only: Option<bool>) -> StorageBalance {
+        let amount = env::attached_deposit();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let registration_only = registration_only.unwrap_or(false);
+        let min_balance = self.storage_balance_bounds().min.0;
+        let refund = match self.storage_accounts.get(&account_id) {
+            Some(_) if registration_only => amount,
+            Some(mut account) => {
+                account.deposit += amount;
+                self.storage_accounts.insert(&account_id, &account);
+                0
+            }
+            None => {
+                require!(amount >= min_balance, "The attached deposit is less than the minimum storage balance");
+                let deposit = if registration_only { min_balance } else { amount };
+                self.storage_accounts.insert(&account_id, &StorageAccount {
+                    deposit,
+                    bytes: self.registration_bytes,
+                });
+                amount - deposit
+            }
+        };
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+        self.storage_balance_of(account_id).unwrap()
+    }
+
+    //function to take back the part of the caller's deposit its data doesn't use, all of it by default
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut account = self.storage_accounts.get(&account_id).expect("Account is not registered");
+        let available = Self::available_balance(&account);
+        let amount = amount.map(|amount| amount.0).unwrap_or(available);
+        require!(amount <= available, "The amount is greater than the available storage balance");
+        if amount > 0 {
+            account.deposit -= amount;
+            self.storage_accounts.insert(&account_id, &account);
+            Promise::new(account_id.clone()).transfer(amount);
+        }
+        self.storage_balance_of(account_id).unwrap()
+    }
+
+    //function to unregister the caller and refund its deposit, everything it wrote has to be
+    //deleted or taken back first as this contract doesn't support `force`
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let account = match self.storage_accounts.get(&account_id) {
+            Some(account) => account,
+            None => return false,
+        };
+        require!(!force.unwrap_or(false), "Force unregistering is not supported");
+        require!(
+            account.bytes <= self.registration_bytes,
+            "Delete the account's posts, comments and profile and undo its reactions, follows, blocks and mutes before unregistering"
+        );
+        self.storage_accounts.remove(&account_id);
+        Promise::new(account_id).transfer(account.deposit);
+        true
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: U128::from(Balance::from(self.registration_bytes) * env::storage_byte_cost()),
+            max: None,
+        }
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.storage_accounts.get(&account_id).map(|account| StorageBalance {
+            total: U128::from(account.deposit),
+            available: U128::from(Self::available_balance(&account)),
+        })
+    }
+}
+
+impl Posts {
+    //bytes a registration takes, measured with the longest possible account id
+    pub(crate) fn measure_registration_bytes(&mut self) {
+        let initial_usage = env::storage_usage();
+        let account_id: AccountId = "a".repeat(MAX_ACCOUNT_ID_LEN).parse().unwrap();
+        self.storage_accounts.insert(&account_id, &StorageAccount { deposit: 0, bytes: 0 });
+        self.registration_bytes = env::storage_usage() - initial_usage;
+        self.storage_accounts.remove(&account_id);
+    }
+
+    fn available_balance(account: &StorageAccount) -> Balance {
+        account.deposit.saturating_sub(Balance::from(account.bytes) * env::storage_byte_cost())
+    }
+
+    //charges the storage written since `initial_usage` to `account_id`, or credits what was freed,
+    //for data only the account writes and removes so what it is credited matches what it paid
+    pub(crate) fn internal_charge_storage(&mut self, account_id: &AccountId, initial_usage: StorageUsage) {
+        let usage = env::storage_usage();
+        if usage >= initial_usage {
+            self.internal_charge_bytes(account_id, usage - initial_usage);
+        } else {
+            self.internal_refund_bytes(account_id, initial_usage - usage);
+        }
+    }
+
+    //charges the storage written since `initial_usage` for a post to its author, or credits what
+    //was freed up to what the post was charged, and saves the post with its new tally. Posts share
+    //their indexes with other posts, so the author is refunded what it paid rather than what is freed
+    pub(crate) fn internal_charge_post_storage(&mut self, post: &mut Post, initial_usage: StorageUsage) {
+        let usage = env::storage_usage();
+        if usage >= initial_usage {
+            let bytes = usage - initial_usage;
+            self.internal_charge_bytes(&post.author, bytes);
+            post.storage_bytes += bytes;
+        } else {
+            let bytes = (initial_usage - usage).min(post.storage_bytes);
+            self.internal_refund_bytes(&post.author, bytes);
+            post.storage_bytes -= bytes;
+        }
+        self.internal_save_post(post);
+    }
+
+    //adds `bytes` to the storage charged to `account_id`, panics when its deposit doesn't cover them
+    pub(crate) fn internal_charge_bytes(&mut self, account_id: &AccountId, bytes: StorageUsage) {
+        let mut account = match self.storage_accounts.get(account_id) {
+            Some(account) => account,
+            None => {
+                require!(bytes == 0, &format!("Account {} is not registered, call storage_deposit first", account_id));
+                return;
+            }
+        };
+        account.bytes += bytes;
+        let needed = Balance::from(account.bytes) * env::storage_byte_cost();
+        if needed > account.deposit {
+            panic!(
+                "Not enough storage deposit for {}: {} yoctoNEAR more needed, call storage_deposit",
+                account_id,
+                needed - account.deposit
+            );
+        }
+        self.storage_accounts.insert(account_id, &account);
+    }
+
+    //takes `bytes` charged earlier off `account_id`, nothing for accounts that are no longer
+    //registered or data from before storage was charged
+    pub(crate) fn internal_refund_bytes(&mut self, account_id: &AccountId, bytes: StorageUsage) {
+        if let Some(mut account) = self.storage_accounts.get(account_id) {
+            account.bytes = account.bytes.saturating_sub(bytes).max(self.registration_bytes);
+            self.storage_accounts.insert(account_id, &account);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{ set_caller, set_deposit };
+    use near_sdk::mock::VmAction;
+    use near_sdk::test_utils::{ accounts, get_created_receipts };
+
+    const NEAR: Balance = 1_000_000_000_000_000_000_000_000;
+
+    fn balance(post: &Posts, account_id: AccountId) -> (u128, u128) {
+        let balance = post.storage_balance_of(account_id).unwrap();
+        (balance.total.0, balance.available.0)
+    }
+
+    //test registering and topping up a deposit
+    #[test]
+    pub fn deposit() {
+        let mut post = Posts::new(accounts(0));
+        let min = post.storage_balance_bounds().min.0;
+        assert!(min > 0);
+        assert!(post.storage_balance_of(accounts(1)).is_none());
+
+        set_deposit(accounts(1), NEAR);
+        post.storage_deposit(None, None);
+        assert_eq!(balance(&post, accounts(1)), (NEAR, NEAR - min));
+        post.storage_deposit(None, None);
+        assert_eq!(balance(&post, accounts(1)).0, 2 * NEAR);
+
+        //someone else can pay for an account, only the minimum is kept with registration_only
+        set_deposit(accounts(2), NEAR);
+        post.storage_deposit(Some(accounts(3)), Some(true));
+        assert_eq!(balance(&post, accounts(3)), (min, 0));
+        assert_eq!(get_created_receipts()[0].receiver_id, accounts(2));
+        assert_eq!(get_created_receipts()[0].actions, vec![VmAction::Transfer { deposit: NEAR - min }]);
+    }
+
+    //test deposits below the minimum
+    #[test]
+    #[should_panic(expected = "The attached deposit is less than the minimum storage balance")]
+    pub fn deposit_below_minimum() {
+        let mut post = Posts::new(accounts(0));
+        set_deposit(accounts(1), 1);
+        post.storage_deposit(None, None);
+    }
+
+    //test posts, comments and profiles are charged to their author and freed on delete
+    #[test]
+    pub fn charge_and_free_storage() {
+        let mut post = Posts::new(accounts(0));
+        set_deposit(accounts(1), NEAR);
+        post.storage_deposit(None, None);
+        let (_, registered) = balance(&post, accounts(1));
+
+        set_caller(accounts(1));
+        post.new_post("title".to_string(), "body @bob.near #tag".to_string(), None);
+        post.edit_post("0".to_string(), "title".to_string(), "edited @bob.near #tag #other".to_string(), None);
+        let (_, after_post) = balance(&post, accounts(1));
+        assert!(after_post < registered);
+        post.set_profile("Bob".to_string(), "bio".to_string(), None, Vec::new());
+        let comment_id = post.add_comment("0".to_string(), "comment".to_string(), None);
+        assert!(balance(&post, accounts(1)).1 < after_post);
+
+        post.delete_comment(comment_id);
+        post.delete_profile();
+        assert_eq!(balance(&post, accounts(1)).1, after_post);
+        post.delete_post("0".to_string());
+        assert_eq!(balance(&post, accounts(1)).1, registered);
+
+        //nothing is left behind
+        set_deposit(accounts(1), 1);
+        assert!(post.storage_unregister(None));
+    }
+
+    //test authors sharing indexes get back what they paid whoever deletes first
+    #[test]
+    pub fn shared_indexes_storage() {
+        let mut post = Posts::new(accounts(0));
+        for account_id in [accounts(1), accounts(2)] {
+            set_deposit(account_id, NEAR);
+            post.storage_deposit(None, None);
+        }
+        let (_, registered) = balance(&post, accounts(1));
+        for account_id in [accounts(1), accounts(2)] {
+            set_caller(account_id);
+            post.new_post("title".to_string(), "shared #tag @carol.near".to_string(), None);
+        }
+        set_caller(accounts(2));
+        let comment_id = post.add_comment("0".to_string(), "comment".to_string(), None);
+        set_caller(accounts(1));
+        let reply_id = post.add_comment("0".to_string(), "reply".to_string(), Some(comment_id.clone()));
+        set_caller(accounts(2));
+        post.delete_comment(comment_id);
+        set_caller(accounts(1));
+        post.delete_comment(reply_id);
+
+        post.delete_post("0".to_string());
+        assert_eq!(balance(&post, accounts(1)).1, registered);
+        set_caller(accounts(2));
+        post.delete_post("1".to_string());
+        assert_eq!(balance(&post, accounts(2)).1, registered);
+
+        for account_id in [accounts(1), accounts(2)] {
+            set_deposit(account_id, 1);
+            assert!(post.storage_unregister(None));
+        }
+    }
+
+    //test reactors get back what they paid whoever takes their reaction back first
+    #[test]
+    pub fn shared_reactions_storage() {
+        let mut post = Posts::new(accounts(0));
+        for account_id in [accounts(1), accounts(2), accounts(3)] {
+            set_deposit(account_id, NEAR);
+            post.storage_deposit(None, None);
+        }
+        let (_, registered) = balance(&post, accounts(2));
+        set_caller(accounts(1));
+        post.new_post("title".to_string(), "body".to_string(), None);
+        for account_id in [accounts(2), accounts(3), accounts(2), accounts(3)] {
+            set_caller(account_id);
+            post.react("0".to_string(), ReactionKind::Like);
+        }
+        for account_id in [accounts(2), accounts(3)] {
+            assert_eq!(balance(&post, account_id.clone()).1, registered);
+            set_deposit(account_id, 1);
+            assert!(post.storage_unregister(None));
+        }
+    }
+
+    //test reactions, follows, blocks and mutes are charged to the caller and freed when taken back
+    #[test]
+    pub fn charge_and_free_social_storage() {
+        let mut post = Posts::new(accounts(0));
+        for account_id in [accounts(1), accounts(2), accounts(3)] {
+            set_deposit(account_id, NEAR);
+            post.storage_deposit(None, None);
+        }
+        let (_, registered) = balance(&post, accounts(2));
+        set_caller(accounts(1));
+        post.new_post("title".to_string(), "body".to_string(), None);
+
+        //both reactors and followers share the sets they are added to
+        for account_id in [accounts(2), accounts(3)] {
+            set_caller(account_id);
+            post.react("0".to_string(), ReactionKind::Like);
+            post.follow(accounts(1));
+        }
+        set_caller(accounts(2));
+        post.react("0".to_string(), ReactionKind::Wow);
+        post.block_account(accounts(4));
+        post.mute_account(accounts(4));
+        assert!(balance(&post, accounts(2)).1 < registered);
+
+        post.react("0".to_string(), ReactionKind::Wow);
+        post.unfollow(accounts(1));
+        post.unblock_account(accounts(4));
+        post.unmute_account(accounts(4));
+        set_caller(accounts(3));
+        post.unfollow(accounts(1));
+
+        //the reactions left are cleared once the post is gone
+        set_caller(accounts(1));
+        post.delete_post("0".to_string());
+        assert!(balance(&post, accounts(2)).1 < registered);
+        assert_eq!(post.clear_reactions("0".to_string(), None), 0);
+        for account_id in [accounts(2), accounts(3)] {
+            assert_eq!(balance(&post, account_id.clone()).1, registered);
+            set_deposit(account_id, 1);
+            assert!(post.storage_unregister(None));
+        }
+    }
+
+    //test reacting without registration
+    #[test]
+    #[should_panic(expected = "is not registered, call storage_deposit first")]
+    pub fn react_without_registration() {
+        let mut post = Posts::new(accounts(0));
+        set_deposit(accounts(1), NEAR);
+        post.storage_deposit(None, None);
+        set_caller(accounts(1));
+        post.new_post("title".to_string(), "body".to_string(), None);
+        set_caller(accounts(2));
+        post.like_post("0".to_string());
+    }
+
+    //test writes from accounts that aren't registered
+    #[test]
+    #[should_panic(expected = "is not registered, call storage_deposit first")]
+    pub fn post_without_registration() {
+        let mut post = Posts::new(accounts(0));
+        set_caller(accounts(1));
+        post.new_post("title".to_string(), "body".to_string(), None);
+    }
+
+    //test writes beyond the deposit
+    #[test]
+    #[should_panic(expected = "Not enough storage deposit for bob")]
+    pub fn post_beyond_deposit() {
+        let mut post = Posts::new(accounts(0));
+        set_deposit(accounts(1), post.storage_balance_bounds().min.0);
+        post.storage_deposit(None, None);
+        set_caller(accounts(1));
+        post.new_post("title".to_string(), "body".to_string(), None);
+    }
+
+    //test withdrawing what the data doesn't use
+    #[test]
+    pub fn withdraw() {
+        let mut post = Posts::new(accounts(0));
+        set_deposit(accounts(1), NEAR);
+        post.storage_deposit(None, None);
+        set_caller(accounts(1));
+        post.new_post("title".to_string(), "body".to_string(), None);
+        let (total, available) = balance(&post, accounts(1));
+
+        set_deposit(accounts(1), 1);
+        post.storage_withdraw(Some(U128::from(100)));
+        assert_eq!(balance(&post, accounts(1)), (total - 100, available - 100));
+        post.storage_withdraw(None);
+        assert_eq!(balance(&post, accounts(1)), (total - available, 0));
+        assert_eq!(get_created_receipts()[1].actions, vec![VmAction::Transfer { deposit: available - 100 }]);
+    }
+
+    //test withdrawing more than is available
+    #[test]
+    #[should_panic(expected = "The amount is greater than the available storage balance")]
+    pub fn withdraw_too_much() {
+        let mut post = Posts::new(accounts(0));
+        set_deposit(accounts(1), NEAR);
+        post.storage_deposit(None, None);
+        set_deposit(accounts(1), 1);
+        post.storage_withdraw(Some(U128::from(NEAR)));
+    }
+
+    //test unregistering hands the deposit back once the data is gone
+    #[test]
+    pub fn unregister() {
+        let mut post = Posts::new(accounts(0));
+        set_deposit(accounts(1), 1);
+        assert!(!post.storage_unregister(None));
+        set_deposit(accounts(1), NEAR);
+        post.storage_deposit(None, None);
+        set_deposit(accounts(1), 1);
+        assert!(post.storage_unregister(None));
+        assert!(post.storage_balance_of(accounts(1)).is_none());
+        assert_eq!(get_created_receipts()[0].actions, vec![VmAction::Transfer { deposit: NEAR }]);
+    }
+
+    //test unregistering with data left
+    #[test]
+    #[should_panic(expected = "Delete the account's posts, comments and profile and undo its reactions, follows, blocks and mutes before unregistering")]
+    pub fn unregister_with_posts() {
+        let mut post = Posts::new(accounts(0));
+        set_deposit(accounts(1), NEAR);
+        post.storage_deposit(None, None);
+        post.new_post("title".to_string(), "body".to_string(), None);
+        set_deposit(accounts(1), 1);
+        post.storage_unregister(None);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::*;
+use std::collections::HashMap;
+
+//longest hashtag kept, without the '#'
+pub const MAX_TAG_LEN: usize = 32;
+//most hashtags kept for a single post
+pub const MAX_TAGS_PER_POST: usize = 10;
+//window of `get_trending_tags` when none is given, about a day of blocks
+pub const DEFAULT_TRENDING_WINDOW: u64 = 86_400;
+//most recent posts looked at by `get_trending_tags`
+pub const MAX_TRENDING_SCAN: usize = 1_000;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TagCount {
+    pub tag: String,
+    pub count: u64,
+}
+
+//function to pick the distinct lowercase #hashtags out of a post, in order of appearance,
+//a '#' only starts a tag at the beginning of a word
+pub fn extract_hashtags(title: &str, body: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for text in [title, body] {
+        let mut previous: Option<char> = None;
+        let mut chars = text.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            let starts_tag = c == '#' && !previous.map(is_tag_char).unwrap_or(false);
+            previous = Some(c);
+            if !starts_tag {
+                continue;
+            }
+            let mut end = i + 1;
+            while let Some((j, next)) = chars.peek() {
+                if !is_tag_char(*next) {
+                    break;
+                }
+                end = j + next.len_utf8();
+                previous = Some(*next);
+                chars.next();
+            }
+            let tag = text[i + 1..end].to_lowercase();
+            if !tag.is_empty() && tag.len() <= MAX_TAG_LEN && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    tags.truncate(MAX_TAGS_PER_POST);
+    tags
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[near_bindgen]
+impl Posts {
+    //function to get the posts using a hashtag, newest first, without hidden posts
+    pub fn get_posts_by_tag(&self, tag: String, from_index: Option<U64>, limit: Option<u64>) -> Vec<Post> {
+        let (from_index, limit) = Self::page(from_index, limit);
+        let tag = tag.trim_start_matches('#').to_lowercase();
+        match self.tag_posts.get(&tag) {
+            Some(post_ids) => post_ids
+                .iter_rev()
+                .filter_map(|(_, post_id)| self.internal_get_post(&post_id))
+                .filter(|post| !post.hidden)
+                .skip(from_index)
+                .take(limit)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    //function to list the hashtags used by the most posts created in the last
+    //`window_blocks` blocks, looking at most at the MAX_TRENDING_SCAN latest posts,
+    //edits count with the tags the post has now but don't make it any more recent,
+    //hidden posts don't count
+    pub fn get_trending_tags(&self, window_blocks: Option<U64>, limit: Option<u64>) -> Vec<TagCount> {
+        let (_, limit) = Self::page(None, limit);
+        let window = window_blocks.map(|window| window.0).unwrap_or(DEFAULT_TRENDING_WINDOW);
+        let since = env::block_height().saturating_sub(window);
+        let mut counts: HashMap<String, u64> = HashMap::new();
+        let recent_posts = self.post_index
+            .iter_rev()
+            .take(MAX_TRENDING_SCAN)
+            .filter_map(|(_, post_id)| self.internal_get_post(&post_id))
+            .take_while(|post| post.block_height.0 >= since);
+        for post in recent_posts.filter(|post| !post.hidden) {
+            for tag in post.tags {
+                *counts.entry(tag).or_insert(0) += 1;
+            }
+        }
+        let mut trending: Vec<TagCount> = counts
+            .into_iter()
+            .map(|(tag, count)| TagCount { tag, count })
+            .collect();
+        trending.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+        trending.truncate(limit);
+        trending
+    }
+}
+
+impl Posts {
+    //adds a post to the feed of each of `tags`
+    pub(crate) fn internal_index_tags(&mut self, post_id: &String, tags: &[String], seq: u64) {
+        for tag in tags {
+            let mut post_ids = self.tag_posts.get(tag).unwrap_or_else(|| {
+                TreeMap::new(StorageKey::TagPostsPerTag { tag_hash: env::sha256_array(tag.as_bytes()) })
+            });
+            post_ids.insert(&seq, post_id);
+            self.tag_posts.insert(tag, &post_ids);
+        }
+    }
+
+    //drops a post from the feed of each of `tags`
+    pub(crate) fn internal_unindex_tags(&mut self, tags: &[String], seq: u64) {
+        for tag in tags {
+            if let Some(mut post_ids) = self.tag_posts.get(tag) {
+                post_ids.remove(&seq);
+                if post_ids.is_empty() {
+                    self.tag_posts.remove(tag);
+                } else {
+                    self.tag_posts.insert(tag, &post_ids);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{ ids, new_contract, set_caller };
+    use near_sdk::test_utils::{ accounts, VMContextBuilder };
+    use near_sdk::testing_env;
+
+    fn tag_count(tag: &str, count: u64) -> TagCount {
+        TagCount { tag: tag.to_string(), count }
+    }
+
+    //sets the block height posts are created at
+    fn set_block(block_height: u64) {
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(1)).block_index(block_height).build());
+    }
+
+    //test hashtags are found in title and body
+    #[test]
+    pub fn extract_tags() {
+        assert_eq!(extract_hashtags("Hello #NEAR", "#rust_lang and #near, #2023!"), vec!["near", "rust_lang", "2023"]);
+        assert_eq!(extract_hashtags("C# isn't a tag, nor is a#b or # alone", "##double"), vec!["double"]);
+        assert_eq!(extract_hashtags("#Über", ""), vec!["über"]);
+        assert!(extract_hashtags(&format!("#{}", "x".repeat(MAX_TAG_LEN + 1)), "").is_empty());
+        let many: Vec<String> = (0..MAX_TAGS_PER_POST + 2).map(|i| format!("#t{}", i)).collect();
+        assert_eq!(extract_hashtags(&many.join(" "), "").len(), MAX_TAGS_PER_POST);
+    }
+
+    //test tag feeds are paginated newest first and follow deletions
+    #[test]
+    pub fn posts_by_tag() {
+        set_caller(accounts(1));
+        let mut post = new_contract();
+        post.new_post("#near one".to_string(), "body".to_string(), None);
+        post.new_post("two".to_string(), "about #NEAR and #rust".to_string(), None);
+        post.new_post("three #near".to_string(), "body".to_string(), None);
+        assert_eq!(post.get_post("1".to_string()).unwrap().tags, vec!["near", "rust"]);
+        assert_eq!(ids(&post.get_posts_by_tag("near".to_string(), None, None)), vec!["2", "1", "0"]);
+        assert_eq!(ids(&post.get_posts_by_tag("#Near".to_string(), Some(U64::from(1)), Some(1))), vec!["1"]);
+
+        post.delete_post("1".to_string());
+        assert_eq!(ids(&post.get_posts_by_tag("near".to_string(), None, None)), vec!["2", "0"]);
+        assert!(post.get_posts_by_tag("rust".to_string(), None, None).is_empty());
+        assert!(post.tag_posts.get(&"rust".to_string()).is_none());
+    }
+
+    //test trending counts only recent posts that still exist
+    #[test]
+    pub fn trending_tags() {
+        set_block(10);
+        let mut post = new_contract();
+        post.new_post("#old".to_string(), "#near".to_string(), None);
+        set_block(100);
+        post.new_post("#near".to_string(), "#rust".to_string(), None);
+        post.new_post("#near".to_string(), "#deleted".to_string(), None);
+        set_block(120);
+        post.new_post("#rust #near".to_string(), "body".to_string(), None);
+        post.delete_post("2".to_string());
+
+        assert_eq!(post.get_trending_tags(Some(U64::from(50)), None), vec![
+            tag_count("near", 2),
+            tag_count("rust", 2)
+        ]);
+        assert_eq!(post.get_trending_tags(Some(U64::from(200)), Some(2)), vec![
+            tag_count("near", 3),
+            tag_count("rust", 2)
+        ]);
+        assert_eq!(post.get_trending_tags(None, None).len(), 3);
+    }
+
+    //test edits count with the post's current tags, at the block it was created
+    #[test]
+    pub fn trending_after_edit() {
+        set_block(10);
+        let mut post = new_contract();
+        post.new_post("#stale".to_string(), "body".to_string(), None);
+        set_block(100);
+        post.new_post("#old".to_string(), "body".to_string(), None);
+        post.edit_post("1".to_string(), "#new".to_string(), "body".to_string(), None);
+        post.edit_post("0".to_string(), "#stale #revived".to_string(), "body".to_string(), None);
+        assert_eq!(post.get_trending_tags(Some(U64::from(50)), None), vec![tag_count("new", 1)]);
+    }
+}
@@ -0,0 +1,156 @@
+// Local CIDv0 computation matching `ipfs add` defaults: 256 KiB chunks,
+// dag-pb UnixFS leaves and a balanced tree of at most 174 links per node.
+// Lets `--dry-run` print the same hash a daemon would return.
+use sha2::{ Digest, Sha256 };
+
+pub const CHUNK_SIZE: usize = 262144;
+pub const MAX_LINKS: usize = 174;
+
+//UnixFS `Data.DataType.File`
+const UNIXFS_FILE: u64 = 2;
+
+//a serialized node of the DAG, as referenced from its parent
+struct Node {
+    multihash: Vec<u8>,
+    //serialized size of the node and everything below it
+    tsize: u64,
+    //number of file bytes below the node
+    filesize: u64,
+}
+
+//function to compute the CIDv0 of a file without talking to a daemon
+pub fn cid_v0(data: &[u8]) -> String {
+    let mut chunks = data.chunks(CHUNK_SIZE).peekable();
+    let mut root = leaf(chunks.next().unwrap_or_default());
+    let mut depth = 1;
+    while chunks.peek().is_some() {
+        let mut children = vec![root];
+        fill(&mut children, &mut chunks, depth);
+        root = branch(children);
+        depth += 1;
+    }
+    bs58::encode(root.multihash).into_string()
+}
+
+//adds children of the given depth until the node is full or the data runs out
+fn fill<'a>(
+    children: &mut Vec<Node>,
+    chunks: &mut std::iter::Peekable<impl Iterator<Item = &'a [u8]>>,
+    depth: usize
+) {
+    while children.len() < MAX_LINKS {
+        if depth == 1 {
+            match chunks.next() {
+                Some(chunk) => children.push(leaf(chunk)),
+                None => return,
+            }
+        } else {
+            if chunks.peek().is_none() {
+                return;
+            }
+            let mut grandchildren = Vec::new();
+            fill(&mut grandchildren, chunks, depth - 1);
+            children.push(branch(grandchildren));
+        }
+    }
+}
+
+fn leaf(chunk: &[u8]) -> Node {
+    let data = unixfs_file(chunk, chunk.len() as u64, &[]);
+    let block = pb_node(&[], &data);
+    Node { multihash: sha256_multihash(&block), tsize: block.len() as u64, filesize: chunk.len() as u64 }
+}
+
+fn branch(children: Vec<Node>) -> Node {
+    let blocksizes: Vec<u64> = children.iter().map(|child| child.filesize).collect();
+    let filesize = blocksizes.iter().sum();
+    let data = unixfs_file(&[], filesize, &blocksizes);
+    let block = pb_node(&children, &data);
+    let tsize = block.len() as u64 + children.iter().map(|child| child.tsize).sum::<u64>();
+    Node { multihash: sha256_multihash(&block), tsize, filesize }
+}
+
+//UnixFS `Data` message: Type = 1, Data = 2, filesize = 3, blocksizes = 4
+fn unixfs_file(data: &[u8], filesize: u64, blocksizes: &[u64]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + 16);
+    put_varint_field(&mut out, 1, UNIXFS_FILE);
+    if !data.is_empty() {
+        put_bytes_field(&mut out, 2, data);
+    }
+    put_varint_field(&mut out, 3, filesize);
+    for size in blocksizes {
+        put_varint_field(&mut out, 4, *size);
+    }
+    out
+}
+
+//dag-pb `PBNode`: links (2) are written before data (1)
+//and every `PBLink` carries Hash = 1, an empty Name = 2 and Tsize = 3
+fn pb_node(links: &[Node], data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + links.len() * 48 + 8);
+    for link in links {
+        let mut encoded = Vec::with_capacity(48);
+        put_bytes_field(&mut encoded, 1, &link.multihash);
+        put_bytes_field(&mut encoded, 2, &[]);
+        put_varint_field(&mut encoded, 3, link.tsize);
+        put_bytes_field(&mut out, 2, &encoded);
+    }
+    put_bytes_field(&mut out, 1, data);
+    out
+}
+
+fn sha256_multihash(block: &[u8]) -> Vec<u8> {
+    let mut multihash = vec![0x12, 0x20];
+    multihash.extend_from_slice(&Sha256::digest(block));
+    multihash
+}
+
+fn put_varint_field(out: &mut Vec<u8>, field: u64, value: u64) {
+    put_varint(out, field << 3);
+    put_varint(out, value);
+}
+
+fn put_bytes_field(out: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    put_varint(out, (field << 3) | 2);
+    put_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //deterministic file contents larger than one chunk
+    fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    //test the CIDs `ipfs add` gives for small files
+    #[test]
+    fn single_chunk_files() {
+        assert_eq!(cid_v0(b""), "QmbFMke1KXqnYyBBWxB74N4c5SBnJMVAiMNRcGu6x1AwQH");
+        assert_eq!(cid_v0(b"hello world\n"), "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o");
+    }
+
+    //test files split over several chunks
+    #[test]
+    fn multi_chunk_files() {
+        assert_eq!(cid_v0(&pattern(CHUNK_SIZE)), "QmeqfRyS3vkku7n6krqC3DgGMex3x2sCpSeKMDmrG13QQq");
+        assert_eq!(cid_v0(&pattern(CHUNK_SIZE + 1)), "QmUSjGawaz4ptvREcMKSMJneWCa5j8dAz2wSAAvHtW2rnB");
+        assert_eq!(cid_v0(&pattern(CHUNK_SIZE * 3 + 100)), "QmZLby23pGa99inuFBsqhnVjckMx3UP5QkdzkskoewRFFG");
+    }
+
+    //test files deep enough to need a second level of links
+    #[test]
+    fn two_level_files() {
+        assert_eq!(cid_v0(&pattern(CHUNK_SIZE * (MAX_LINKS + 2))), "QmQCJuhJksHT5uHiDDa2QvkSVwdnV9KqiXq5hMAyVHfHCm");
+    }
+}
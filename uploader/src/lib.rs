@@ -0,0 +1,159 @@
+// Off-chain helper for the social_near contract: adds a local file to IPFS
+// and prints the `near call ... new_post` command with its CID as `image`.
+use ipfs_api::{ IpfsApi, IpfsClient, TryFromUri };
+use std::fs::File;
+use std::path::{ Path, PathBuf };
+
+pub mod cid;
+
+pub const USAGE: &str = "usage: uploader <file> [--api <url>] [--dry-run] \
+[--contract <account>] [--account <account>] [--title <title>] [--body <body>]";
+
+//command line options
+#[derive(Clone, PartialEq, Debug)]
+pub struct Options {
+    pub file: PathBuf,
+    //IPFS HTTP API, the local daemon when not set
+    pub api: Option<String>,
+    //compute the CID locally instead of adding the file
+    pub dry_run: bool,
+    pub contract: String,
+    pub account: String,
+    pub title: String,
+    pub body: String,
+}
+
+//function to parse the command line, without the program name
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+    let mut file = None;
+    let mut options = Options {
+        file: PathBuf::new(),
+        api: None,
+        dry_run: false,
+        contract: "<contract-account>".to_string(),
+        account: "<your-account>".to_string(),
+        title: String::new(),
+        body: String::new(),
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--api" => options.api = Some(value()?),
+            "--dry-run" => options.dry_run = true,
+            "--contract" => options.contract = value()?,
+            "--account" => options.account = value()?,
+            "--title" => options.title = value()?,
+            "--body" => options.body = value()?,
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ if file.is_some() => return Err(format!("unexpected argument {}", arg)),
+            _ => file = Some(PathBuf::from(arg)),
+        }
+    }
+    options.file = file.ok_or("missing file")?;
+    Ok(options)
+}
+
+//function to build the IPFS client for the configured API
+pub fn client(api: Option<&str>) -> Result<IpfsClient, String> {
+    match api {
+        Some(url) => IpfsClient::from_str(url).map_err(|err| format!("invalid IPFS API url '{}': {}", url, err)),
+        None => Ok(IpfsClient::default()),
+    }
+}
+
+//function to write a file into ipfs, returning its CID
+pub async fn add_file(client: &IpfsClient, path: &Path) -> Result<String, String> {
+    let data = File::open(path).map_err(|err| format!("couldn't open '{}': {}", path.display(), err))?;
+    client
+        .add(data)
+        .await
+        .map(|res| res.hash)
+        .map_err(|err| format!("couldn't add '{}' to IPFS: {}", path.display(), err))
+}
+
+//function to compute the CID `add_file` would return, without a daemon
+pub fn dry_run_cid(path: &Path) -> Result<String, String> {
+    std::fs::read(path)
+        .map(|data| cid::cid_v0(&data))
+        .map_err(|err| format!("couldn't read '{}': {}", path.display(), err))
+}
+
+//function to render the near-cli call creating a post with the image
+pub fn new_post_command(options: &Options, image: &str) -> String {
+    let args = serde_json::json!({
+        "title": options.title,
+        "body": options.body,
+        "image": image,
+    });
+    format!(
+        "near call {} new_post {} --accountId {}",
+        options.contract,
+        shell_quote(&args.to_string()),
+        options.account
+    )
+}
+
+//single quotes a string for POSIX shells
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    //test parsing every option
+    #[test]
+    fn parse_all_options() {
+        let options = parse_args(
+            args(&[
+                "photo.jpg",
+                "--api",
+                "http://127.0.0.1:5002",
+                "--dry-run",
+                "--contract",
+                "social.testnet",
+                "--account",
+                "alice.testnet",
+                "--title",
+                "title",
+                "--body",
+                "body",
+            ])
+        ).unwrap();
+        assert_eq!(options.file, PathBuf::from("photo.jpg"));
+        assert_eq!(options.api, Some("http://127.0.0.1:5002".to_string()));
+        assert!(options.dry_run);
+        assert_eq!(options.contract, "social.testnet");
+        assert_eq!(options.account, "alice.testnet");
+        assert_eq!(options.title, "title");
+        assert_eq!(options.body, "body");
+    }
+
+    //test invalid command lines are rejected
+    #[test]
+    fn parse_invalid_options() {
+        assert_eq!(parse_args(args(&[])), Err("missing file".to_string()));
+        assert_eq!(parse_args(args(&["a.jpg", "--title"])), Err("missing value for --title".to_string()));
+        assert_eq!(parse_args(args(&["a.jpg", "--force"])), Err("unknown option --force".to_string()));
+        assert_eq!(parse_args(args(&["a.jpg", "b.jpg"])), Err("unexpected argument b.jpg".to_string()));
+    }
+
+    //test the printed near call is valid JSON inside shell quotes
+    #[test]
+    fn render_new_post_command() {
+        let mut options = parse_args(args(&["a.jpg", "--contract", "social.testnet"])).unwrap();
+        options.title = "it's \"mine\"".to_string();
+        assert_eq!(
+            new_post_command(&options, "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o"),
+            "near call social.testnet new_post \
+'{\"body\":\"\",\"image\":\"QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o\",\"title\":\"it'\\''s \\\"mine\\\"\"}' \
+--accountId <your-account>"
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use std::process;
+use uploader::{ add_file, client, dry_run_cid, new_post_command, parse_args, USAGE };
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    let options = parse_args(args).unwrap_or_else(|err| {
+        eprintln!("{}\n{}", err, USAGE);
+        process::exit(2);
+    });
+
+    let cid = if options.dry_run {
+        dry_run_cid(&options.file)
+    } else {
+        match client(options.api.as_deref()) {
+            Ok(client) => add_file(&client, &options.file).await,
+            Err(err) => Err(err),
+        }
+    };
+    match cid {
+        Ok(cid) => {
+            println!("{}", cid);
+            println!("{}", new_post_command(&options, &cid));
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+}
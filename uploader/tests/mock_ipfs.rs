@@ -0,0 +1,209 @@
+// Runs the uploader against an in-process stand-in for the IPFS HTTP API,
+// so nothing here needs a real daemon.
+use std::io::{ BufRead, BufReader, Read, Write };
+use std::net::{ TcpListener, TcpStream };
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{ Arc, Mutex };
+use std::thread;
+use uploader::{ add_file, client, cid::CHUNK_SIZE };
+
+//CID `ipfs add` gives for "hello world\n"
+const HELLO_CID: &str = "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o";
+//CID `ipfs add` gives for `pattern(CHUNK_SIZE + 1)`, a file of two chunks
+const PATTERN_CID: &str = "QmUSjGawaz4ptvREcMKSMJneWCa5j8dAz2wSAAvHtW2rnB";
+
+//request seen by the mock: path with query and the uploaded file
+type Received = Arc<Mutex<Vec<(String, Vec<u8>)>>>;
+
+//deterministic file contents larger than one chunk
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+//hash the mock answers with, pinned for the files the tests upload so that
+//nothing here is checked against the uploader's own CID computation
+fn pinned_cid(file: &[u8]) -> Option<&'static str> {
+    if file == b"hello world\n" {
+        Some(HELLO_CID)
+    } else if file == pattern(CHUNK_SIZE + 1) {
+        Some(PATTERN_CID)
+    } else {
+        None
+    }
+}
+
+//starts a mock `/api/v0/add` endpoint, returning its url and what it receives
+fn mock_ipfs() -> (String, Received) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let received: Received = Arc::new(Mutex::new(Vec::new()));
+    let log = received.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            handle(stream.unwrap(), &log);
+        }
+    });
+    (url, received)
+}
+
+fn handle(stream: TcpStream, log: &Received) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let path = request_line.split_whitespace().nth(1).unwrap_or_default().to_string();
+
+    let mut content_length = None;
+    let mut chunked = false;
+    let mut boundary = String::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(':').unwrap();
+        let value = value.trim();
+        match name.to_ascii_lowercase().as_str() {
+            "content-length" => content_length = value.parse::<usize>().ok(),
+            "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+            "content-type" => {
+                boundary = value.split("boundary=").nth(1).unwrap_or_default().trim_matches('"').to_string();
+            }
+            _ => {}
+        }
+    }
+
+    let body = if chunked {
+        read_chunked(&mut reader)
+    } else {
+        let mut body = vec![0; content_length.unwrap_or(0)];
+        reader.read_exact(&mut body).unwrap();
+        body
+    };
+    let file = multipart_file(&body, &boundary);
+
+    let (status, response) = if !path.starts_with("/api/v0/add") {
+        ("404 Not Found", r#"{"Message":"unknown command","Code":0,"Type":"error"}"#.to_string())
+    } else if let Some(hash) = pinned_cid(&file) {
+        let response = format!(r#"{{"Name":"{}","Hash":"{}","Size":"{}"}}"#, hash, hash, file.len());
+        ("200 OK", response)
+    } else {
+        ("500 Internal Server Error", r#"{"Message":"no pinned CID for this file","Code":0,"Type":"error"}"#.to_string())
+    };
+    log.lock().unwrap().push((path, file));
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        response.len(),
+        response
+    ).unwrap();
+}
+
+fn read_chunked(reader: &mut impl BufRead) -> Vec<u8> {
+    let mut body = Vec::new();
+    loop {
+        let mut size = String::new();
+        reader.read_line(&mut size).unwrap();
+        let size = usize::from_str_radix(size.trim(), 16).unwrap();
+        let mut chunk = vec![0; size + 2];
+        reader.read_exact(&mut chunk).unwrap();
+        if size == 0 {
+            return body;
+        }
+        body.extend_from_slice(&chunk[..size]);
+    }
+}
+
+//content of the first part of a multipart/form-data body
+fn multipart_file(body: &[u8], boundary: &str) -> Vec<u8> {
+    let find = |haystack: &[u8], needle: &[u8]| haystack.windows(needle.len()).position(|w| w == needle);
+    let start = find(body, b"\r\n\r\n").map(|i| i + 4).unwrap_or(0);
+    let end = find(&body[start..], format!("\r\n--{}", boundary).as_bytes())
+        .map(|i| start + i)
+        .unwrap_or(body.len());
+    body[start..end].to_vec()
+}
+
+fn temp_file(name: &str, content: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("uploader-{}-{}", std::process::id(), name));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+//test adding a file goes through `/api/v0/add` and returns its hash
+#[tokio::test]
+async fn add_file_to_mock() {
+    let (url, received) = mock_ipfs();
+    let content = b"hello world\n".to_vec();
+    let path = temp_file("add.jpg", &content);
+
+    let cid = add_file(&client(Some(&url)).unwrap(), &path).await.unwrap();
+
+    assert_eq!(cid, HELLO_CID);
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 1);
+    assert!(received[0].0.starts_with("/api/v0/add"));
+    assert_eq!(received[0].1, content);
+}
+
+//test a daemon that can't be reached is reported as an error
+#[tokio::test]
+async fn add_file_without_daemon() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    let path = temp_file("offline.jpg", b"offline");
+
+    let err = add_file(&client(Some(&url)).unwrap(), &path).await.unwrap_err();
+
+    assert!(err.starts_with("couldn't add"), "{}", err);
+}
+
+//test the binary prints the CID and the near call using it
+#[test]
+fn prints_new_post_call() {
+    let (url, _) = mock_ipfs();
+    let path = temp_file("cli.jpg", b"hello world\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_uploader"))
+        .args([path.to_str().unwrap(), "--api", &url, "--contract", "social.testnet"])
+        .args(["--account", "alice.testnet", "--title", "title", "--body", "body"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o\n\
+near call social.testnet new_post \
+'{\"body\":\"body\",\"image\":\"QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o\",\"title\":\"title\"}' \
+--accountId alice.testnet\n"
+    );
+}
+
+//test dry run gives the CID the daemon returns for the upload, without any API
+#[test]
+fn dry_run_matches_upload() {
+    let (url, _) = mock_ipfs();
+    let content = pattern(CHUNK_SIZE + 1);
+    let path = temp_file("dry.jpg", &content);
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_uploader"))
+            .arg(path.to_str().unwrap())
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let uploaded = run(&["--api", &url]);
+    let dry_run = run(&["--dry-run", "--api", "http://127.0.0.1:1"]);
+
+    assert!(uploaded.starts_with(PATTERN_CID), "{}", uploaded);
+    assert_eq!(uploaded, dry_run);
+}